@@ -0,0 +1,256 @@
+//! Dapps defined by the `lambada-app` CID, fetched from IPFS and launched by salsa.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::io::{Error, ErrorKind};
+use std::path::{Component, Path};
+
+use cid::Cid;
+use serde::{Deserialize, Serialize};
+use sha3::{Digest, Sha3_256};
+
+use crate::config::Config;
+use crate::ipfs::{Ipfs, IpfsError};
+use crate::manifest::ProcessSpec;
+use crate::rollup::{client::RollupClient, METADATA};
+
+/// Metadata entry holding the CID of the app
+pub const LAMBADA_APP: &str = "lambada-app";
+/// File at the root of the app telling how to start it
+pub const APP_MANIFEST: &str = "app.json";
+/// MFS directory the app is copied to
+pub const APP_PATH: &str = "/app";
+/// MFS directory the app is verified in before it replaces `APP_PATH`
+const APP_NEW_PATH: &str = "/app-new";
+/// File marking a local directory salsa fetched an app into, the only ones it replaces
+const APP_DIR_MARKER: &str = ".salsa-app";
+
+#[derive(Debug)]
+pub enum AppError {
+    Ipfs(IpfsError),
+    /// The app does not ship a valid manifest or is not the pinned one
+    Invalid(String),
+}
+
+impl fmt::Display for AppError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AppError::Ipfs(e) => write!(f, "{}", e),
+            AppError::Invalid(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for AppError {}
+
+impl From<IpfsError> for AppError {
+    fn from(e: IpfsError) -> Self {
+        AppError::Ipfs(e)
+    }
+}
+
+/// How to start the dapp, read from the app manifest
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct AppManifest {
+    /// Program started for the dapp, taken from the app directory when it ships one by that name
+    pub entrypoint: String,
+    #[serde(default)]
+    pub args: Vec<String>,
+    /// Variables added to the dapp environment, under the ones given on the command line
+    #[serde(default)]
+    pub env: BTreeMap<String, String>,
+}
+
+impl AppManifest {
+    pub fn parse(data: &[u8]) -> Result<Self, String> {
+        let manifest: Self = serde_json::from_slice(data)
+            .map_err(|e| format!("{} is not valid: {}", APP_MANIFEST, e))?;
+        if manifest.entrypoint.is_empty() {
+            return Err(format!("{} has an empty entrypoint", APP_MANIFEST));
+        }
+        // The entrypoint is made executable, it should not reach out of the app directory
+        let entrypoint = Path::new(&manifest.entrypoint);
+        if entrypoint.is_absolute()
+            || entrypoint
+                .components()
+                .any(|component| matches!(component, Component::ParentDir))
+        {
+            return Err(format!(
+                "{} entrypoint {} should be a path inside the app",
+                APP_MANIFEST, manifest.entrypoint
+            ));
+        }
+        Ok(manifest)
+    }
+
+    /// Read the manifest at the root of the app directory `dir`
+    pub fn load(dir: &Path) -> std::io::Result<Self> {
+        let data = std::fs::read(dir.join(APP_MANIFEST))?;
+        Self::parse(&data).map_err(|e| Error::new(ErrorKind::InvalidData, e))
+    }
+
+    /// The dapp process, started from the app directory `dir` unless a workdir is configured
+    pub fn dapp(self, dir: &Path, config: &Config) -> std::io::Result<ProcessSpec> {
+        let entrypoint = dir.join(&self.entrypoint);
+        let program = if entrypoint.is_file() {
+            let entrypoint = entrypoint.canonicalize()?;
+            if !entrypoint.starts_with(dir.canonicalize()?) {
+                return Err(Error::new(
+                    ErrorKind::InvalidData,
+                    format!("entrypoint {:?} is outside of the app", entrypoint),
+                ));
+            }
+            // Files read from MFS lose their mode
+            #[cfg(unix)]
+            {
+                use std::os::unix::fs::PermissionsExt;
+                std::fs::set_permissions(&entrypoint, std::fs::Permissions::from_mode(0o755))?;
+            }
+            entrypoint.display().to_string()
+        } else {
+            self.entrypoint
+        };
+        let mut command = vec![program];
+        command.extend(self.args);
+        let mut dapp = ProcessSpec::dapp(command, config);
+        let mut env = self.env;
+        env.append(&mut dapp.env);
+        dapp.env = env;
+        dapp.cwd.get_or_insert_with(|| dir.to_path_buf());
+        Ok(dapp)
+    }
+}
+
+/// Resolve the app CID from the `lambada-app` metadata
+pub async fn app_cid(rollup: &RollupClient) -> std::io::Result<Cid> {
+    let response = rollup
+        .call(METADATA, Sha3_256::digest(LAMBADA_APP.as_bytes()))
+        .await
+        .map_err(|e| Error::other(format!("failed to resolve {}: {}", LAMBADA_APP, e)))?;
+    let bytes = response
+        .response_bytes()
+        .map_err(|e| Error::new(ErrorKind::InvalidData, e))?;
+    Cid::try_from(bytes).map_err(|e| {
+        Error::new(
+            ErrorKind::InvalidData,
+            format!("{} is not a valid cid: {}", LAMBADA_APP, e),
+        )
+    })
+}
+
+/// Check that `cid` is the app pinned with `--app-cid`. The CID covers every file of the app,
+/// the CIDv0 and CIDv1 of the same tree are the same app.
+pub fn check_pinned(cid: &Cid, pinned: Option<&str>) -> Result<(), AppError> {
+    let Some(pinned) = pinned else {
+        return Ok(());
+    };
+    let expected = Cid::try_from(pinned)
+        .map_err(|e| AppError::Invalid(format!("pinned app cid {} is not valid: {}", pinned, e)))?;
+    if (cid.codec(), cid.hash()) != (expected.codec(), expected.hash()) {
+        return Err(AppError::Invalid(format!(
+            "app {} is not the pinned app {}",
+            cid, pinned
+        )));
+    }
+    Ok(())
+}
+
+/// Copy the app `cid` to MFS `/app` once it is verified, `/app` is left untouched when the app
+/// is not valid
+pub async fn install(
+    ipfs: &Ipfs,
+    cid: &Cid,
+    pinned: Option<&str>,
+) -> Result<AppManifest, AppError> {
+    check_pinned(cid, pinned)?;
+    // Leftover of an install that did not finish
+    let _ = ipfs.files_rm(APP_NEW_PATH).await;
+    ipfs.files_cp(&format!("/ipfs/{}", cid), APP_NEW_PATH)
+        .await?;
+    let verified = verify(ipfs, cid).await;
+    let manifest = match verified {
+        Ok(manifest) => manifest,
+        Err(e) => {
+            let _ = ipfs.files_rm(APP_NEW_PATH).await;
+            return Err(e);
+        }
+    };
+    // Nothing to remove on the first install
+    let _ = ipfs.files_rm(APP_PATH).await;
+    ipfs.files_mv(APP_NEW_PATH, APP_PATH).await?;
+    Ok(manifest)
+}
+
+async fn verify(ipfs: &Ipfs, cid: &Cid) -> Result<AppManifest, AppError> {
+    let path = format!("{}/{}", APP_NEW_PATH, APP_MANIFEST);
+    if ipfs.files_stat_hash(&path).await.is_err() {
+        return Err(AppError::Invalid(format!(
+            "app {} has no {}",
+            cid, APP_MANIFEST
+        )));
+    }
+    let data = ipfs.files_read(&path).await?;
+    AppManifest::parse(&data).map_err(AppError::Invalid)
+}
+
+/// Copy the MFS tree at `from` into the local directory `to`
+pub async fn export(ipfs: &Ipfs, from: &str, to: &Path) -> std::io::Result<()> {
+    let mut pending = vec![(from.to_string(), to.to_path_buf())];
+    while let Some((from, to)) = pending.pop() {
+        std::fs::create_dir_all(&to)?;
+        let entries = ipfs.files_ls(&from).await.map_err(Error::other)?;
+        for (name, is_dir) in entries {
+            let source = format!("{}/{}", from, name);
+            if is_dir {
+                pending.push((source, to.join(name)));
+            } else {
+                let data = ipfs.files_read(&source).await.map_err(Error::other)?;
+                std::fs::write(to.join(name), data)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Make `dir` ready to receive an app, refusing to replace a non-empty directory salsa did not
+/// fetch an app into
+pub fn prepare_dir(dir: &Path) -> std::io::Result<()> {
+    match std::fs::read_dir(dir) {
+        Ok(mut entries) => {
+            if dir.join(APP_DIR_MARKER).is_file() {
+                std::fs::remove_dir_all(dir)?;
+            } else if entries.next().is_some() {
+                return Err(Error::new(
+                    ErrorKind::AlreadyExists,
+                    format!(
+                        "{:?} is not empty and was not created by salsa, refusing to replace it",
+                        dir
+                    ),
+                ));
+            }
+        }
+        Err(e) if e.kind() == ErrorKind::NotFound => {}
+        Err(e) => return Err(e),
+    }
+    std::fs::create_dir_all(dir)?;
+    // Marked before the export, so that an export that did not finish is replaced next time
+    std::fs::write(dir.join(APP_DIR_MARKER), b"")
+}
+
+/// Fetch the app defined by the `lambada-app` CID into `dir` and return the dapp it starts
+pub async fn fetch(
+    config: &Config,
+    rollup: &RollupClient,
+    dir: &Path,
+) -> std::io::Result<ProcessSpec> {
+    let ipfs = Ipfs::new(config)?;
+    let cid = app_cid(rollup).await?;
+    log::info!("fetching app {} into {:?}", cid, dir);
+    install(&ipfs, &cid, config.app_cid.as_deref())
+        .await
+        .map_err(Error::other)?;
+    prepare_dir(dir)?;
+    export(&ipfs, APP_PATH, dir).await?;
+    AppManifest::load(dir)?.dapp(dir, config)
+}
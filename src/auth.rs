@@ -0,0 +1,277 @@
+//! Optional authentication of the http service, configured per group of routes.
+//!
+//! A group either requires a static bearer token or requests signed with HMAC-SHA256. A
+//! signed request carries the unix time in seconds in `x-salsa-timestamp` and the hex encoded
+//! signature of [`sign`] in `x-salsa-signature`. Requests without credentials are answered
+//! with 401 and requests with wrong ones with 403.
+
+use std::str::FromStr;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use actix_web::body::{EitherBody, MessageBody};
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::http::header::{HeaderMap, AUTHORIZATION, WWW_AUTHENTICATE};
+use actix_web::http::Uri;
+use actix_web::middleware::Next;
+use actix_web::web;
+use actix_web::{HttpRequest, HttpResponse};
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+
+use crate::config::{AuthConfig, AuthMethod, Config, Secret};
+use crate::{http_service, utils};
+
+/// Header carrying the unix time in seconds at which a request was signed
+pub const TIMESTAMP_HEADER: &str = "x-salsa-timestamp";
+/// Header carrying the hex encoded HMAC-SHA256 signature of a request
+pub const SIGNATURE_HEADER: &str = "x-salsa-signature";
+
+/// Routes sharing the same credentials
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RouteGroup {
+    /// Reading the state and IPFS blocks
+    ReadState,
+    /// Changing the state, the IPFS blocks or the app
+    MutateState,
+    /// Passing requests through to the rollup server
+    Gio,
+}
+
+impl RouteGroup {
+    /// Group of the route `pattern`, `None` for the routes that are always open
+    pub fn of(pattern: &str) -> Option<Self> {
+        match pattern {
+            "/get_state/{key}" | "/ipfs/get/{cid}" | "/ipfs/has/{cid}" => {
+                Some(RouteGroup::ReadState)
+            }
+            "/set_state/{key}"
+            | "/delete_state/{key}"
+            | "/open_state"
+            | "/commit_state"
+            | "/ipfs/put/{cid}"
+            | "/get_app" => Some(RouteGroup::MutateState),
+            "/metadata/{text}"
+            | "/get_data/{namespace}/{data_id}"
+            | "/hint/{what}"
+            | "/exception" => Some(RouteGroup::Gio),
+            _ => None,
+        }
+    }
+}
+
+impl FromStr for RouteGroup {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "read_state" => Ok(RouteGroup::ReadState),
+            "mutate_state" => Ok(RouteGroup::MutateState),
+            "gio" => Ok(RouteGroup::Gio),
+            _ => Err(format!(
+                "unknown route group '{}', expected read_state, mutate_state or gio",
+                s
+            )),
+        }
+    }
+}
+
+/// Hex encoded HMAC-SHA256 with `secret` of the timestamp, the method, the path with its
+/// query and the body, the first three each followed by a newline
+pub fn sign(secret: &str, timestamp: u64, method: &str, path: &str, body: &[u8]) -> String {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("hmac accepts any key size");
+    mac.update(format!("{}\n{}\n{}\n", timestamp, method, path).as_bytes());
+    mac.update(body);
+    hex::encode(mac.finalize().into_bytes())
+}
+
+/// Why a request is not let through
+#[derive(Debug)]
+pub enum Denied {
+    /// No credentials, answered with 401
+    Missing(String),
+    /// Wrong credentials, answered with 403
+    Invalid(String),
+}
+
+impl Denied {
+    pub fn reason(&self) -> &str {
+        match self {
+            Denied::Missing(reason) | Denied::Invalid(reason) => reason,
+        }
+    }
+}
+
+/// Compare without leaking where the first difference is through the timing
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+fn header<'a>(headers: &'a HeaderMap, name: &str) -> Option<&'a str> {
+    headers.get(name).and_then(|value| value.to_str().ok())
+}
+
+fn check_bearer(headers: &HeaderMap, token: &Secret) -> Result<(), Denied> {
+    let given = header(headers, AUTHORIZATION.as_str())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .ok_or_else(|| Denied::Missing("missing bearer token".to_string()))?;
+    if constant_time_eq(given.trim().as_bytes(), token.expose().as_bytes()) {
+        Ok(())
+    } else {
+        Err(Denied::Invalid("invalid bearer token".to_string()))
+    }
+}
+
+/// Headers of a signed request, checked before its body is read
+struct Signed {
+    timestamp: u64,
+    signature: String,
+}
+
+impl Signed {
+    /// Read the signature headers and refuse a timestamp further than `max_skew` from now
+    fn from_headers(headers: &HeaderMap, max_skew: Duration) -> Result<Self, Denied> {
+        let (Some(timestamp), Some(signature)) = (
+            header(headers, TIMESTAMP_HEADER),
+            header(headers, SIGNATURE_HEADER),
+        ) else {
+            return Err(Denied::Missing(format!(
+                "missing {} or {}",
+                TIMESTAMP_HEADER, SIGNATURE_HEADER
+            )));
+        };
+        let timestamp: u64 = timestamp
+            .parse()
+            .map_err(|_| Denied::Invalid(format!("{} is not valid", TIMESTAMP_HEADER)))?;
+        if !max_skew.is_zero() {
+            let now = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs();
+            if now.abs_diff(timestamp) > max_skew.as_secs() {
+                return Err(Denied::Invalid(format!(
+                    "{} is more than {:?} away from the clock of salsa",
+                    TIMESTAMP_HEADER, max_skew
+                )));
+            }
+        }
+        Ok(Self {
+            timestamp,
+            signature: signature.to_ascii_lowercase(),
+        })
+    }
+
+    fn check(&self, secret: &Secret, method: &str, path: &str, body: &[u8]) -> Result<(), Denied> {
+        let expected = sign(secret.expose(), self.timestamp, method, path, body);
+        if constant_time_eq(self.signature.as_bytes(), expected.as_bytes()) {
+            Ok(())
+        } else {
+            Err(Denied::Invalid("invalid signature".to_string()))
+        }
+    }
+}
+
+fn path_and_query(uri: &Uri) -> String {
+    uri.path_and_query()
+        .map_or_else(|| uri.path().to_string(), |path| path.to_string())
+}
+
+/// Check the credentials of a request of `group` whose body was already read, as the
+/// JSON-RPC calls are authenticated one by one
+pub fn verify(
+    req: &HttpRequest,
+    body: &[u8],
+    config: &AuthConfig,
+    group: RouteGroup,
+) -> Result<(), Denied> {
+    match config.method(group) {
+        None => Ok(()),
+        Some(AuthMethod::Bearer(token)) => check_bearer(req.headers(), token),
+        Some(AuthMethod::Hmac(secret)) => {
+            Signed::from_headers(req.headers(), config.hmac_max_skew)?.check(
+                secret,
+                req.method().as_str(),
+                &path_and_query(req.uri()),
+                body,
+            )
+        }
+    }
+}
+
+/// Let the request through when its route group requires no credentials or it carries
+/// the right ones
+pub async fn authenticate(
+    mut req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<EitherBody<impl MessageBody>>, actix_web::Error> {
+    let pattern = req.match_pattern();
+    let group = pattern.as_deref().and_then(RouteGroup::of);
+    let method = match (group, req.app_data::<web::Data<Config>>()) {
+        (Some(group), Some(config)) => config
+            .auth
+            .method(group)
+            .cloned()
+            .map(|method| (method, config.clone())),
+        _ => None,
+    };
+    let checked = match &method {
+        None => Ok(()),
+        Some((AuthMethod::Bearer(token), _)) => check_bearer(req.headers(), token),
+        Some((AuthMethod::Hmac(secret), config)) => {
+            match Signed::from_headers(req.headers(), config.auth.hmac_max_skew) {
+                Err(denied) => Err(denied),
+                Ok(signed) => {
+                    // The body is read to be signed and handed back to the handler, bounded
+                    // as it is held in memory
+                    let (what, limit) = match pattern.as_deref().and_then(|p| config.body_limit(p))
+                    {
+                        Some((route, limit)) => (format!("request body of {}", route), limit),
+                        None => ("request body".to_string(), config.body_limits.default),
+                    };
+                    match utils::read_body(&mut req, limit).await {
+                        Ok(Some(body)) => {
+                            let checked = signed.check(
+                                secret,
+                                req.method().as_str(),
+                                &path_and_query(req.uri()),
+                                &body,
+                            );
+                            utils::restore_body(&mut req, body);
+                            checked
+                        }
+                        Ok(None) => {
+                            log::warn!(
+                                "rejected {} {}: {} is over {} bytes",
+                                req.method(),
+                                req.path(),
+                                what,
+                                limit
+                            );
+                            let response = http_service::payload_too_large(&what, limit);
+                            return Ok(req.into_response(response).map_into_right_body());
+                        }
+                        Err(e) => Err(Denied::Invalid(format!("failed to read body: {}", e))),
+                    }
+                }
+            }
+        }
+    };
+    let response = match checked {
+        Ok(()) => return Ok(next.call(req).await?.map_into_left_body()),
+        Err(Denied::Missing(reason)) => {
+            let challenge = match method {
+                Some((AuthMethod::Hmac(_), _)) => "HMAC-SHA256",
+                _ => "Bearer",
+            };
+            log::warn!("rejected {} {}: {}", req.method(), req.path(), reason);
+            HttpResponse::Unauthorized()
+                .insert_header((WWW_AUTHENTICATE, challenge))
+                .body(reason)
+        }
+        Err(Denied::Invalid(reason)) => {
+            log::warn!("rejected {} {}: {}", req.method(), req.path(), reason);
+            HttpResponse::Forbidden().body(reason)
+        }
+    };
+    Ok(req.into_response(response).map_into_right_body())
+}
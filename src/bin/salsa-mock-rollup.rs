@@ -0,0 +1,104 @@
+use std::io::ErrorKind;
+
+use getopts::Options;
+use salsa::config::Listener;
+use salsa::mock::MockRollup;
+
+fn print_usage(program: &str, opts: Options) {
+    let brief = format!(
+        "Usage: {} [options]\n\
+        \n\
+        Emulates the rollup http server GIO and exception endpoints.",
+        program
+    );
+    print!("{}", opts.usage(&brief));
+}
+
+fn invalid_input(message: String) -> std::io::Error {
+    eprintln!("{}", message);
+    std::io::Error::new(ErrorKind::InvalidInput, message)
+}
+
+#[actix_web::main]
+async fn main() -> std::io::Result<()> {
+    let args: Vec<String> = std::env::args().collect();
+    let program = args[0].clone();
+    let mut opts = Options::new();
+    opts.optflag("h", "help", "show this help message and exit");
+    opts.optopt(
+        "",
+        "address",
+        "Address to listen (default: 127.0.0.1:5004)",
+        "",
+    );
+    opts.optopt("", "state-cid", "Initial state CID", "CID");
+    opts.optmulti(
+        "",
+        "metadata",
+        "Metadata entry, the value is hex encoded",
+        "NAME=HEX",
+    );
+    opts.optmulti(
+        "",
+        "preimage",
+        "Preimage returned for a get_data request, both sides are hex encoded",
+        "KEY=HEX",
+    );
+    opts.optflag("", "verbose", "print more info about application execution");
+    let matches = match opts.parse(&args[1..]) {
+        Ok(m) => m,
+        Err(e) => return Err(invalid_input(format!("error parsing arguments: {}", e))),
+    };
+    if matches.opt_present("h") {
+        print_usage(&program, opts);
+        return Ok(());
+    }
+
+    let log_level = if matches.opt_present("verbose") {
+        "debug"
+    } else {
+        "info"
+    };
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or(log_level))
+        .format_timestamp(None)
+        .init();
+
+    let mock = MockRollup::new();
+    if let Some(cid) = matches.opt_str("state-cid") {
+        let cid = cid::Cid::try_from(cid.as_str())
+            .map_err(|e| invalid_input(format!("state cid is not valid: {}", e)))?;
+        mock.set_state_cid(cid.to_bytes());
+    }
+    for entry in matches.opt_strs("metadata") {
+        let (name, value) = entry
+            .split_once('=')
+            .ok_or_else(|| invalid_input(format!("metadata '{}' is not NAME=HEX", entry)))?;
+        let value = hex::decode(value.trim_start_matches("0x"))
+            .map_err(|e| invalid_input(format!("metadata '{}' is not valid: {}", name, e)))?;
+        mock.set_metadata(name, value);
+    }
+    for entry in matches.opt_strs("preimage") {
+        let (key, data) = entry
+            .split_once('=')
+            .ok_or_else(|| invalid_input(format!("preimage '{}' is not KEY=HEX", entry)))?;
+        let data = hex::decode(data.trim_start_matches("0x"))
+            .map_err(|e| invalid_input(format!("preimage '{}' is not valid: {}", key, e)))?;
+        mock.set_preimage(key.as_bytes(), data);
+    }
+
+    let address = matches
+        .opt_get_default("address", "127.0.0.1:5004".to_string())
+        .unwrap_or_default();
+    let (host, port) = match address.parse::<Listener>().map_err(invalid_input)? {
+        Listener::Tcp { address, port } => (address, port),
+        Listener::Unix(_) => {
+            return Err(invalid_input(format!(
+                "address '{}' is not a tcp address",
+                address
+            )))
+        }
+    };
+
+    log::info!("starting mock rollup server on {}", address);
+    mock.serve(&host, port)?.await
+}
@@ -1,7 +1,464 @@
-#[derive(Debug, Clone, PartialEq, Eq)]
+use std::collections::BTreeMap;
+use std::fmt;
+use std::io::{Error, ErrorKind};
+use std::net::Ipv6Addr;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::Duration;
+
+use log::LevelFilter;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::auth::RouteGroup;
+use crate::telemetry::LogFormat;
+
+/// Prefix of the environment variables overriding the config file
+pub const ENV_PREFIX: &str = "SALSA_";
+
+/// Fields holding maps whose keys are chosen by the user, such as variable names
+const MAP_FIELDS: &[&str] = &["dapp_env"];
+/// Written in place of secrets when the configuration is serialized
+pub const REDACTED: &str = "<redacted>";
+
+/// Size of the transmit buffer of the Cartesi machine, which holds the payload of a GIO request
+pub const MAX_GIO_PAYLOAD: usize = 2 * 1024 * 1024;
+
+/// Durations are serialized as milliseconds
+fn serialize_ms<S: Serializer>(duration: &Duration, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_u64(duration.as_millis() as u64)
+}
+
+fn deserialize_ms<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Duration, D::Error> {
+    u64::deserialize(deserializer).map(Duration::from_millis)
+}
+
+/// Settings of salsa, every field can be set from the config file, see [`Config::load`]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
 pub struct Config {
     pub http_address: String,
     pub http_port: u16,
+    /// Addresses the http service listens on, `http_address:http_port` alone when empty
+    pub listen: Vec<Listener>,
+    /// Base url of the rollup http server that answers GIO requests and exceptions
+    pub rollup_url: String,
+    /// Base url of the IPFS daemon API used for MFS operations
+    pub ipfs_url: String,
+    /// Maximum time a single GIO request may take before it is abandoned
+    #[serde(serialize_with = "serialize_ms", deserialize_with = "deserialize_ms")]
+    pub gio_timeout: Duration,
+    /// Number of extra attempts for GIO requests on idempotent domains
+    pub gio_retries: u32,
+    /// Delay before the first retry, doubled on every following attempt
+    #[serde(serialize_with = "serialize_ms", deserialize_with = "deserialize_ms")]
+    pub gio_backoff: Duration,
+    /// Maximum time a single IPFS call may take before it is abandoned
+    #[serde(serialize_with = "serialize_ms", deserialize_with = "deserialize_ms")]
+    pub ipfs_timeout: Duration,
+    /// Cache GIO responses on domains that are pure functions of their payload
+    pub gio_cache_enabled: bool,
+    /// Maximum number of responses kept in the GIO cache
+    pub gio_cache_entries: usize,
+    /// Maximum size in bytes of the hex encoded payloads and responses kept in the GIO cache
+    pub gio_cache_bytes: usize,
+    /// Largest payload of a GIO request, larger ones are refused before yielding
+    pub gio_max_payload: usize,
+    /// Largest request body accepted by each route
+    pub body_limits: BodyLimits,
+    /// Append every GIO exchange to this JSONL trace file
+    pub gio_record: Option<PathBuf>,
+    /// Answer GIO requests from this JSONL trace file instead of the rollup server
+    pub gio_replay: Option<PathBuf>,
+    /// Answer GIO requests from a local backend instead of the rollup server
+    pub dev: Option<DevConfig>,
+    /// When the dapp is started again after it exits
+    pub dapp_restart: RestartPolicy,
+    /// Maximum number of restarts within `dapp_restart_window` before giving up
+    pub dapp_max_restarts: u32,
+    /// Sliding window in which restarts are counted
+    #[serde(serialize_with = "serialize_ms", deserialize_with = "deserialize_ms")]
+    pub dapp_restart_window: Duration,
+    /// Delay before the first restart in a window, doubled on every following restart
+    #[serde(serialize_with = "serialize_ms", deserialize_with = "deserialize_ms")]
+    pub dapp_restart_backoff: Duration,
+    /// Pipe the dapp stdout and stderr through the logger instead of inheriting them
+    pub dapp_capture_output: bool,
+    /// Level at which captured dapp stdout lines are logged
+    pub dapp_stdout_level: LevelFilter,
+    /// Level at which captured dapp stderr lines are logged
+    pub dapp_stderr_level: LevelFilter,
+    /// Number of trailing stderr lines kept to be reported when the dapp exits
+    pub dapp_stderr_tail: usize,
+    /// Time given to in-flight requests and the dapp to finish once salsa is shutting down
+    #[serde(serialize_with = "serialize_ms", deserialize_with = "deserialize_ms")]
+    pub shutdown_grace: Duration,
+    /// How to tell that the dapp is ready to serve, it is ready once started when unset
+    pub dapp_readiness: Option<ReadinessProbe>,
+    /// Time the dapp has to become ready before a rollup exception is thrown
+    #[serde(serialize_with = "serialize_ms", deserialize_with = "deserialize_ms")]
+    pub dapp_startup_timeout: Duration,
+    /// Resource limits applied to the dapp
+    pub dapp_limits: ResourceLimits,
+    /// Variables added to the dapp environment, over the ones salsa sets
+    pub dapp_env: BTreeMap<String, String>,
+    /// Working directory of the dapp, salsa's one when unset
+    pub dapp_workdir: Option<PathBuf>,
+    /// Start the dapp with an empty environment instead of inheriting salsa's
+    pub dapp_clear_env: bool,
+    /// CID the app has to be, pinning every file of it
+    pub app_cid: Option<String>,
+    /// Maximum time the readiness endpoint waits for each backend
+    #[serde(serialize_with = "serialize_ms", deserialize_with = "deserialize_ms")]
+    pub probe_timeout: Duration,
+    /// Level of the logs, overridden by `RUST_LOG`
+    pub log_level: LevelFilter,
+    pub log_format: LogFormat,
+    /// Append finished tracing spans to this file in the OTLP/JSON format
+    pub trace_file: Option<PathBuf>,
+    /// Credentials required by each group of routes
+    pub auth: AuthConfig,
+    /// Answer cross-origin requests from browsers, refused when unset
+    pub cors: Option<CorsConfig>,
+}
+
+/// Cross-origin requests allowed from browsers, such as the frontend of a dapp in development
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CorsConfig {
+    /// Origins allowed to send requests, `*` allows any
+    pub allowed_origins: Vec<String>,
+    pub allowed_methods: Vec<String>,
+    /// Request headers allowed besides the ones browsers always allow
+    pub allowed_headers: Vec<String>,
+    /// Seconds browsers may cache the answer to a preflight request
+    pub max_age: Option<usize>,
+}
+
+impl Default for CorsConfig {
+    fn default() -> Self {
+        Self {
+            allowed_origins: vec!["*".to_string()],
+            allowed_methods: ["GET", "HEAD", "POST", "PUT", "DELETE"]
+                .map(String::from)
+                .to_vec(),
+            allowed_headers: [
+                "authorization",
+                "content-type",
+                "x-request-id",
+                "x-salsa-timestamp",
+                "x-salsa-signature",
+            ]
+            .map(String::from)
+            .to_vec(),
+            max_age: Some(3600),
+        }
+    }
+}
+
+/// Largest request body in bytes accepted by each route taking one
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct BodyLimits {
+    pub set_state: usize,
+    /// Also bounded by `gio_max_payload`, the block is sent as a GIO payload
+    pub ipfs_put: usize,
+    pub exception: usize,
+    /// Applies to the hint in the path, also bounded by `gio_max_payload`
+    pub hint: usize,
+    /// Whole JSON-RPC request, the decoded values of its calls are also bounded by the limit
+    /// of the matching route
+    pub rpc: usize,
+    /// Routes without a limit of their own, bodies are only read there to check a signature
+    pub default: usize,
+}
+
+impl Default for BodyLimits {
+    fn default() -> Self {
+        Self {
+            set_state: 64 * 1024 * 1024,
+            ipfs_put: MAX_GIO_PAYLOAD,
+            exception: 64 * 1024,
+            hint: MAX_GIO_PAYLOAD,
+            // Room for a base64 encoded `set_state` value
+            rpc: 96 * 1024 * 1024,
+            default: 64 * 1024,
+        }
+    }
+}
+
+/// Credentials required by each group of routes, the routes of a group without any are open
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AuthConfig {
+    /// Routes reading the state and IPFS blocks
+    pub read_state: Option<AuthMethod>,
+    /// Routes changing the state, the IPFS blocks or the app
+    pub mutate_state: Option<AuthMethod>,
+    /// Routes passing requests through to the rollup server
+    pub gio: Option<AuthMethod>,
+    /// Maximum difference between the timestamp of a signed request and the clock of salsa,
+    /// not checked when zero
+    #[serde(serialize_with = "serialize_ms", deserialize_with = "deserialize_ms")]
+    pub hmac_max_skew: Duration,
+}
+
+impl Default for AuthConfig {
+    fn default() -> Self {
+        Self {
+            read_state: None,
+            mutate_state: None,
+            gio: None,
+            hmac_max_skew: Duration::from_secs(300),
+        }
+    }
+}
+
+impl AuthConfig {
+    pub fn method(&self, group: RouteGroup) -> Option<&AuthMethod> {
+        match group {
+            RouteGroup::ReadState => self.read_state.as_ref(),
+            RouteGroup::MutateState => self.mutate_state.as_ref(),
+            RouteGroup::Gio => self.gio.as_ref(),
+        }
+    }
+
+    pub fn set_method(&mut self, group: RouteGroup, method: AuthMethod) {
+        match group {
+            RouteGroup::ReadState => self.read_state = Some(method),
+            RouteGroup::MutateState => self.mutate_state = Some(method),
+            RouteGroup::Gio => self.gio = Some(method),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AuthMethod {
+    /// The request carries `Authorization: Bearer <token>`
+    Bearer(Secret),
+    /// The request is signed with HMAC-SHA256 of the secret, see [`crate::auth::sign`]
+    Hmac(Secret),
+}
+
+/// Credential kept out of logs and of the serialized configuration
+#[derive(Clone, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "String")]
+pub struct Secret(String);
+
+impl Secret {
+    pub fn new(secret: impl Into<String>) -> Self {
+        Self(secret.into())
+    }
+
+    pub fn expose(&self) -> &str {
+        &self.0
+    }
+}
+
+impl TryFrom<String> for Secret {
+    type Error = String;
+
+    /// Refuses the placeholder, a printed configuration loaded back would use it as the secret
+    fn try_from(secret: String) -> Result<Self, Self::Error> {
+        if secret == REDACTED {
+            return Err(format!(
+                "secret is {}, fill in the one the printed configuration left out",
+                REDACTED
+            ));
+        }
+        Ok(Self(secret))
+    }
+}
+
+impl fmt::Debug for Secret {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Secret({})", REDACTED)
+    }
+}
+
+impl Serialize for Secret {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(REDACTED)
+    }
+}
+
+/// Address the http service listens on, written `host:port`, `[ipv6]:port` or `unix:/path`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum Listener {
+    Tcp {
+        address: String,
+        port: u16,
+    },
+    /// Unix domain socket, access is controlled by the permissions of the file
+    Unix(PathBuf),
+}
+
+impl Listener {
+    /// Base url of the http service on this listener, `unix:` followed by the path of a socket
+    pub fn url(&self) -> String {
+        match self {
+            Listener::Tcp { address, port } if address.contains(':') => {
+                format!("http://[{}]:{}", address, port)
+            }
+            Listener::Tcp { address, port } => format!("http://{}:{}", address, port),
+            Listener::Unix(path) => format!("unix:{}", path.display()),
+        }
+    }
+}
+
+impl fmt::Display for Listener {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Listener::Tcp { address, port } if address.contains(':') => {
+                write!(f, "[{}]:{}", address, port)
+            }
+            Listener::Tcp { address, port } => write!(f, "{}:{}", address, port),
+            Listener::Unix(path) => write!(f, "unix:{}", path.display()),
+        }
+    }
+}
+
+impl FromStr for Listener {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Some(path) = s.strip_prefix("unix:") {
+            if path.is_empty() {
+                return Err(format!("address '{}' has no socket path", s));
+            }
+            return Ok(Listener::Unix(PathBuf::from(path)));
+        }
+        let (address, port) = match s.strip_prefix('[') {
+            Some(rest) => {
+                let (address, port) = rest
+                    .split_once("]:")
+                    .ok_or_else(|| format!("address '{}' is not [ipv6]:port", s))?;
+                address
+                    .parse::<Ipv6Addr>()
+                    .map_err(|e| format!("address '{}' is not valid: {}", s, e))?;
+                (address, port)
+            }
+            None => {
+                let (address, port) = s
+                    .rsplit_once(':')
+                    .ok_or_else(|| format!("address '{}' is not host:port", s))?;
+                if address.contains(':') {
+                    return Err(format!(
+                        "address '{}' is not valid, write ipv6 addresses as [address]:port",
+                        s
+                    ));
+                }
+                (address, port)
+            }
+        };
+        if address.is_empty() {
+            return Err(format!("address '{}' has no host", s));
+        }
+        let port = port
+            .parse::<u16>()
+            .map_err(|e| format!("port of address '{}' is not valid: {}", s, e))?;
+        Ok(Listener::Tcp {
+            address: address.to_string(),
+            port,
+        })
+    }
+}
+
+impl TryFrom<String> for Listener {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+impl From<Listener> for String {
+    fn from(listener: Listener) -> Self {
+        listener.to_string()
+    }
+}
+
+/// Limits set on a process before it executes, unset ones are inherited from salsa
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ResourceLimits {
+    /// Maximum size of the virtual memory in bytes (`RLIMIT_AS`)
+    pub address_space: Option<u64>,
+    /// Maximum number of open file descriptors (`RLIMIT_NOFILE`)
+    pub open_files: Option<u64>,
+    /// Maximum CPU time in seconds (`RLIMIT_CPU`)
+    pub cpu_time: Option<u64>,
+    /// Seconds after which the process is terminated
+    pub wall_clock: Option<u64>,
+}
+
+impl ResourceLimits {
+    /// Whether a limit enforced by the kernel is set, as opposed to the wall-clock deadline
+    pub fn has_rlimits(&self) -> bool {
+        self.address_space.is_some() || self.open_files.is_some() || self.cpu_time.is_some()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReadinessProbe {
+    /// The url answers a GET with a success status
+    Http(String),
+    /// The address accepts TCP connections
+    Tcp(String),
+    /// The file exists
+    File(PathBuf),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum RestartPolicy {
+    /// Throw a rollup exception as soon as the dapp exits
+    #[default]
+    Never,
+    /// Restart the dapp when it exits unsuccessfully
+    OnFailure,
+    /// Restart the dapp whenever it exits
+    Always,
+}
+
+impl FromStr for RestartPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "never" => Ok(RestartPolicy::Never),
+            "on-failure" => Ok(RestartPolicy::OnFailure),
+            "always" => Ok(RestartPolicy::Always),
+            _ => Err(format!(
+                "unknown restart policy '{}', expected never, on-failure or always",
+                s
+            )),
+        }
+    }
+}
+
+/// Local GIO backend used by `salsa dev` to run outside the Cartesi machine
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DevConfig {
+    /// Directory holding the current state CID and the blockstore
+    pub data_dir: PathBuf,
+    /// JSON object mapping metadata names to their values
+    pub metadata_file: Option<PathBuf>,
+    /// Directory with one file per `get_data` preimage, named after the data id
+    pub preimages_dir: Option<PathBuf>,
+}
+
+impl Default for DevConfig {
+    fn default() -> Self {
+        Self {
+            data_dir: PathBuf::from(".salsa"),
+            metadata_file: None,
+            preimages_dir: None,
+        }
+    }
 }
 
 impl Default for Config {
@@ -15,6 +472,204 @@ impl Config {
         Self {
             http_address: String::from("127.0.0.1"),
             http_port: 5005,
+            listen: Vec::new(),
+            rollup_url: String::from("http://127.0.0.1:5004"),
+            ipfs_url: String::from("http://127.0.0.1:5001"),
+            gio_timeout: Duration::from_secs(30),
+            gio_retries: 3,
+            gio_backoff: Duration::from_millis(100),
+            ipfs_timeout: Duration::from_secs(30),
+            gio_cache_enabled: true,
+            gio_cache_entries: 1024,
+            gio_cache_bytes: 64 * 1024 * 1024,
+            gio_max_payload: MAX_GIO_PAYLOAD,
+            body_limits: BodyLimits::default(),
+            gio_record: None,
+            gio_replay: None,
+            dev: None,
+            dapp_restart: RestartPolicy::Never,
+            dapp_max_restarts: 5,
+            dapp_restart_window: Duration::from_secs(60),
+            dapp_restart_backoff: Duration::from_secs(1),
+            dapp_capture_output: true,
+            dapp_stdout_level: LevelFilter::Info,
+            dapp_stderr_level: LevelFilter::Warn,
+            dapp_stderr_tail: 20,
+            shutdown_grace: Duration::from_secs(10),
+            dapp_readiness: None,
+            dapp_startup_timeout: Duration::from_secs(60),
+            dapp_limits: ResourceLimits::default(),
+            dapp_env: BTreeMap::new(),
+            dapp_workdir: None,
+            dapp_clear_env: false,
+            app_cid: None,
+            probe_timeout: Duration::from_secs(1),
+            log_level: LevelFilter::Info,
+            log_format: LogFormat::Text,
+            trace_file: None,
+            auth: AuthConfig::default(),
+            cors: None,
         }
     }
-}
\ No newline at end of file
+
+    /// Layer the TOML config `file` and then the `SALSA_*` variables of `env` over the
+    /// defaults. A variable is named after the field it sets, `SALSA_GIO_TIMEOUT` sets
+    /// `gio_timeout`, with `__` separating the fields of nested tables as in
+    /// `SALSA_DAPP_LIMITS__CPU_TIME`. Variables naming no field are ignored.
+    pub fn load(
+        file: Option<&Path>,
+        env: impl IntoIterator<Item = (String, String)>,
+    ) -> std::io::Result<Self> {
+        let mut table = match file {
+            Some(path) => {
+                let text = std::fs::read_to_string(path)?;
+                let table: toml::Table = toml::from_str(&text).map_err(|e| {
+                    Error::new(ErrorKind::InvalidData, format!("{}: {}", path.display(), e))
+                })?;
+                Self::from_table(&table).map_err(|e| {
+                    Error::new(ErrorKind::InvalidData, format!("{}: {}", path.display(), e))
+                })?;
+                table
+            }
+            None => toml::Table::new(),
+        };
+        let fields = match serde_json::to_value(Self::new()) {
+            Ok(serde_json::Value::Object(fields)) => fields,
+            _ => unreachable!("config serializes to an object"),
+        };
+        let mut env: Vec<(String, String, String)> = env
+            .into_iter()
+            .filter_map(|(name, value)| {
+                let key = env_key(name.strip_prefix(ENV_PREFIX)?);
+                let field = key.first()?.clone();
+                fields
+                    .contains_key(&field)
+                    .then_some((key.join("__"), name, value))
+            })
+            .collect();
+        env.sort();
+        for (key, name, value) in env {
+            Self::apply_env(&mut table, &key, &value).map_err(|e| {
+                Error::new(
+                    ErrorKind::InvalidData,
+                    format!("{} is not valid: {}", name, e),
+                )
+            })?;
+        }
+        let config = Self::from_table(&table)
+            .map_err(|e| Error::new(ErrorKind::InvalidData, e.to_string()))?;
+        if config.gio_record.is_some() && config.gio_replay.is_some() {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                "gio_record and gio_replay can not be used together",
+            ));
+        }
+        Ok(config)
+    }
+
+    fn from_table(table: &toml::Table) -> Result<Self, toml::de::Error> {
+        table.clone().try_into()
+    }
+
+    /// Set `key` to `value` read as a TOML value, or as a plain string when that does not fit,
+    /// the error tells why the plain string does not fit either
+    fn apply_env(table: &mut toml::Table, key: &str, value: &str) -> Result<(), String> {
+        let parsed = format!("value = {}", value)
+            .parse::<toml::Table>()
+            .ok()
+            .and_then(|mut parsed| parsed.remove("value"));
+        let path: Vec<&str> = key.split("__").collect();
+        let mut reason = String::new();
+        for value in parsed
+            .into_iter()
+            .chain([toml::Value::String(value.to_string())])
+        {
+            let mut candidate = table.clone();
+            insert_path(&mut candidate, &path, value);
+            match Self::from_table(&candidate) {
+                Ok(_) => {
+                    *table = candidate;
+                    return Ok(());
+                }
+                Err(e) => reason = e.message().to_string(),
+            }
+        }
+        Err(reason)
+    }
+
+    /// Name and body limit of the route `pattern`, `None` for routes without a body
+    pub fn body_limit(&self, pattern: &str) -> Option<(&'static str, usize)> {
+        match pattern {
+            "/set_state/{key}" => Some(("set_state", self.body_limits.set_state)),
+            "/ipfs/put/{cid}" => Some((
+                "ipfs_put",
+                self.body_limits.ipfs_put.min(self.gio_max_payload),
+            )),
+            "/exception" => Some(("exception", self.body_limits.exception)),
+            "/rpc" => Some(("rpc", self.body_limits.rpc)),
+            _ => None,
+        }
+    }
+
+    /// Largest hint accepted by the hint route
+    pub fn hint_limit(&self) -> usize {
+        self.body_limits.hint.min(self.gio_max_payload)
+    }
+
+    /// Addresses the http service listens on
+    pub fn listeners(&self) -> Vec<Listener> {
+        if self.listen.is_empty() {
+            vec![Listener::Tcp {
+                address: self.http_address.clone(),
+                port: self.http_port,
+            }]
+        } else {
+            self.listen.clone()
+        }
+    }
+
+    /// Base url under which the http service is reachable, the one of its first TCP listener
+    /// when it has one
+    pub fn salsa_url(&self) -> String {
+        let listeners = self.listeners();
+        listeners
+            .iter()
+            .find(|listener| matches!(listener, Listener::Tcp { .. }))
+            .unwrap_or(&listeners[0])
+            .url()
+    }
+}
+
+/// Key path of the variable `name` without its prefix, split on `__`. Field names are
+/// lowercased, the keys of the maps in `MAP_FIELDS` are kept as they are.
+fn env_key(name: &str) -> Vec<String> {
+    let mut in_map = false;
+    name.split("__")
+        .map(|segment| {
+            if in_map {
+                return segment.to_string();
+            }
+            let segment = segment.to_lowercase();
+            in_map = MAP_FIELDS.contains(&segment.as_str());
+            segment
+        })
+        .collect()
+}
+
+/// Set the value at `path`, creating the missing tables on the way
+fn insert_path(table: &mut toml::Table, path: &[&str], value: toml::Value) {
+    match path {
+        [] => {}
+        [last] => {
+            table.insert(last.to_string(), value);
+        }
+        [first, rest @ ..] => {
+            let entry = table
+                .entry(first.to_string())
+                .or_insert_with(|| toml::Value::Table(toml::Table::new()));
+            if let Some(nested) = entry.as_table_mut() {
+                insert_path(nested, rest, value);
+            }
+        }
+    }
+}
@@ -14,36 +14,747 @@
 // limitations under the License.
 //
 
-use crate::{rollup::Exception, utils};
+use std::collections::{BTreeMap, VecDeque};
+use std::process::Stdio;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use crate::config::{Config, ResourceLimits, RestartPolicy};
+use crate::manifest::{ProcessSpec, DAPP};
+use crate::readiness;
+use crate::rollup::{client::RollupClient, ExceptionReport};
+use crate::shutdown::{self, SIGTERM};
+use log::LevelFilter;
+use serde::Serialize;
+use tokio::io::{AsyncBufReadExt, AsyncRead, BufReader};
 use tokio::process::Command;
+use tokio::sync::watch;
+use utoipa::ToSchema;
+
+/// Snapshot of the supervised dapp, served by the status endpoint
+#[derive(Debug, Clone, Default, Serialize, ToSchema)]
+pub struct ProcessState {
+    pub name: String,
+    /// A dapp command was given, salsa is only ready once the dapp is
+    pub supervised: bool,
+    pub running: bool,
+    /// The readiness probe succeeded for the current run of the dapp
+    pub ready: bool,
+    pub pid: Option<u32>,
+    pub starts: u32,
+    pub restarts: u32,
+    pub restarts_in_window: u32,
+    pub last_exit: Option<String>,
+    /// The restart policy gave up and the rollup exception was thrown
+    pub exhausted: bool,
+    /// The dapp did not exit within the shutdown grace period and was killed
+    pub killed: bool,
+}
+
+/// Shared view of the dapp lifecycle, updated by the supervisor
+#[derive(Debug)]
+pub struct ProcessStatus {
+    state: watch::Sender<ProcessState>,
+    /// Signal to forward to the dapp once salsa is shutting down
+    shutdown: watch::Sender<Option<i32>>,
+}
+
+impl Default for ProcessStatus {
+    fn default() -> Self {
+        Self {
+            state: watch::channel(ProcessState::default()).0,
+            shutdown: watch::channel(None).0,
+        }
+    }
+}
+
+impl ProcessStatus {
+    /// Status of a dapp that is about to be started
+    pub fn supervised() -> Self {
+        Self::named(DAPP)
+    }
+
+    /// Status of a manifest process that is about to be started
+    pub fn named(name: &str) -> Self {
+        let status = Self::default();
+        status.update(|state| {
+            state.name = name.to_string();
+            state.supervised = true;
+        });
+        status
+    }
+
+    pub fn snapshot(&self) -> ProcessState {
+        self.state.borrow().clone()
+    }
+
+    /// Stop supervising, forwarding `signal` to the running dapp
+    pub fn shutdown(&self, signal: i32) {
+        self.shutdown.send_replace(Some(signal));
+    }
+
+    /// Whether the dapp, if any, passed its readiness probe
+    pub fn is_ready(&self) -> bool {
+        let state = self.state.borrow();
+        !state.supervised || state.ready
+    }
+
+    /// Wait until the process is ready or its supervisor gave up, returns whether it is ready
+    async fn wait_ready(&self) -> bool {
+        let mut state = self.state.subscribe();
+        let ready = state
+            .wait_for(|state| state.ready || state.exhausted)
+            .await
+            .map(|state| state.ready);
+        ready.unwrap_or(false)
+    }
+
+    pub fn is_shutting_down(&self) -> bool {
+        self.shutdown.borrow().is_some()
+    }
+
+    async fn shutdown_signal(&self) -> i32 {
+        let mut shutdown = self.shutdown.subscribe();
+        let signal = shutdown
+            .wait_for(Option::is_some)
+            .await
+            .map(|signal| signal.unwrap_or(SIGTERM));
+        match signal {
+            Ok(signal) => signal,
+            // The sender lives as long as self, so this never happens
+            Err(_) => std::future::pending().await,
+        }
+    }
+
+    fn update(&self, f: impl FnOnce(&mut ProcessState)) {
+        self.state.send_modify(f)
+    }
+}
+
+/// How long the output of an exited dapp is still read
+const OUTPUT_DRAIN_TIMEOUT: Duration = Duration::from_secs(1);
+/// Delay between two samples of the resources used by a process with rlimits
+const USAGE_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Exception kind of a dapp that started and exited
+pub const DAPP_EXIT: &str = "dapp_exit";
+/// Exception kind of a dapp that could not be started
+pub const DAPP_START_FAILURE: &str = "dapp_start_failure";
+/// Exception kind of a dapp that did not become ready within the startup timeout
+pub const DAPP_NOT_READY: &str = "dapp_not_ready";
+/// Exception kind of a dapp that exceeded one of its resource limits, named in `details.limit`
+pub const DAPP_LIMIT_EXCEEDED: &str = "dapp_limit_exceeded";
+
+/// How a single run of the dapp ended
+struct Exit {
+    success: bool,
+    report: ExceptionReport,
+}
+
+fn unix_time_ms(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis() as u64
+}
+
+#[cfg(unix)]
+fn exit_signal(status: &std::process::ExitStatus) -> Option<i32> {
+    use std::os::unix::process::ExitStatusExt;
+    status.signal()
+}
+
+#[cfg(not(unix))]
+fn exit_signal(_status: &std::process::ExitStatus) -> Option<i32> {
+    None
+}
+
+/// Variable holding the base url of the salsa http service
+pub const SALSA_URL: &str = "SALSA_URL";
+/// Variable holding the base url of the rollup http server
+pub const ROLLUP_HTTP_SERVER_URL: &str = "ROLLUP_HTTP_SERVER_URL";
+/// Variable holding the url of the IPFS daemon API
+pub const IPFS_API_URL: &str = "IPFS_API_URL";
+
+/// Variables set for a process, the urls of salsa and its backends and then its own ones
+fn process_env(config: &Config, spec: &ProcessSpec) -> BTreeMap<String, String> {
+    let mut env = BTreeMap::from([
+        (SALSA_URL.to_string(), config.salsa_url()),
+        (
+            ROLLUP_HTTP_SERVER_URL.to_string(),
+            config.rollup_url.clone(),
+        ),
+        (IPFS_API_URL.to_string(), config.ipfs_url.clone()),
+    ]);
+    env.extend(spec.env.clone());
+    env
+}
+
+/// Set the rlimits of the calling process, run in the child between fork and exec
+#[cfg(unix)]
+fn apply_rlimits(limits: &ResourceLimits) -> std::io::Result<()> {
+    let rlimits = [
+        (libc::RLIMIT_AS, limits.address_space, 0),
+        (libc::RLIMIT_NOFILE, limits.open_files, 0),
+        // One second of slack so SIGXCPU is sent before the hard limit kills the process
+        (libc::RLIMIT_CPU, limits.cpu_time, 1),
+    ];
+    for (resource, limit, slack) in rlimits {
+        if let Some(limit) = limit {
+            let rlimit = libc::rlimit {
+                rlim_cur: limit as libc::rlim_t,
+                rlim_max: limit.saturating_add(slack) as libc::rlim_t,
+            };
+            // SAFETY: setrlimit is async-signal-safe and only reads the rlimit passed to it
+            if unsafe { libc::setrlimit(resource, &rlimit) } != 0 {
+                return Err(std::io::Error::last_os_error());
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Highest use of the rlimited resources seen while a process ran
+#[derive(Debug, Default, Clone, Copy)]
+struct PeakUsage {
+    /// Open file descriptors
+    open_files: u64,
+    /// Virtual memory size in bytes
+    address_space: u64,
+}
+
+/// Record in `peak` what the process `pid` uses, read from procfs until the task is aborted.
+/// Nothing is recorded where there is no procfs.
+async fn sample_usage(pid: u32, peak: Arc<Mutex<PeakUsage>>) {
+    let fd_dir = format!("/proc/{}/fd", pid);
+    let status_file = format!("/proc/{}/status", pid);
+    loop {
+        let mut open_files = 0;
+        if let Ok(mut entries) = tokio::fs::read_dir(&fd_dir).await {
+            while let Ok(Some(_)) = entries.next_entry().await {
+                open_files += 1;
+            }
+        }
+        // VmPeak is the highest virtual memory size of the process so far, in kB
+        let address_space = tokio::fs::read_to_string(&status_file)
+            .await
+            .ok()
+            .and_then(|status| {
+                let line = status
+                    .lines()
+                    .find_map(|line| line.strip_prefix("VmPeak:"))?;
+                line.trim()
+                    .trim_end_matches("kB")
+                    .trim()
+                    .parse::<u64>()
+                    .ok()
+            })
+            .map_or(0, |kb| kb * 1024);
+        {
+            let mut peak = peak.lock().unwrap();
+            peak.open_files = peak.open_files.max(open_files);
+            peak.address_space = peak.address_space.max(address_space);
+        }
+        tokio::time::sleep(USAGE_INTERVAL).await;
+    }
+}
+
+/// The rlimit a failed process exceeded, with its configured value. CPU time is told by the
+/// signal the kernel sends. Memory and file descriptors only fail the call asking for more, so
+/// they are told by an exit code of ENOMEM or EMFILE, or by the sampled usage reaching the
+/// limit, within 10% for the address space since the allocation that failed is not counted.
+#[cfg(unix)]
+fn exceeded_limit(
+    limits: &ResourceLimits,
+    exit_status: &std::process::ExitStatus,
+    peak: &PeakUsage,
+) -> Option<(&'static str, u64)> {
+    if exit_status.success() {
+        return None;
+    }
+    if let (Some(cpu_time), Some(libc::SIGXCPU)) = (limits.cpu_time, exit_signal(exit_status)) {
+        return Some(("cpu_time", cpu_time));
+    }
+    let code = exit_status.code();
+    if let Some(open_files) = limits.open_files {
+        if code == Some(libc::EMFILE) || peak.open_files >= open_files {
+            return Some(("open_files", open_files));
+        }
+    }
+    if let Some(address_space) = limits.address_space {
+        if code == Some(libc::ENOMEM) || peak.address_space >= address_space / 10 * 9 {
+            return Some(("address_space", address_space));
+        }
+    }
+    None
+}
+
+#[cfg(not(unix))]
+fn exceeded_limit(
+    _limits: &ResourceLimits,
+    _exit_status: &std::process::ExitStatus,
+    _peak: &PeakUsage,
+) -> Option<(&'static str, u64)> {
+    None
+}
 
-/// Execute the dapp command and throw a rollup exception if it fails or exits
-pub async fn run(args: Vec<String>) {
-    log::info!("starting dapp: {}", args.join(" "));
-    let task = tokio::task::spawn_blocking(move || Command::new(&args[0]).args(&args[1..]).spawn());
-    let message = match task.await {
+/// Log every line of a dapp output stream, keeping the last `tail_size` lines in `tail`
+async fn forward_output<R>(
+    stream: R,
+    name: &'static str,
+    pid: u32,
+    level: LevelFilter,
+    tail: Option<(Arc<Mutex<VecDeque<String>>>, usize)>,
+) where
+    R: AsyncRead + Unpin,
+{
+    let mut reader = BufReader::new(stream);
+    let mut buffer = Vec::new();
+    loop {
+        buffer.clear();
+        match reader.read_until(b'\n', &mut buffer).await {
+            Ok(0) => break,
+            Ok(_) => {
+                let line = String::from_utf8_lossy(&buffer);
+                let line = line.trim_end_matches(['\r', '\n']);
+                if let Some(level) = level.to_level() {
+                    log::log!(target: "dapp", level, "[{} {}] {}", name, pid, line);
+                }
+                if let Some((tail, size)) = &tail {
+                    let mut tail = tail.lock().unwrap();
+                    if tail.len() == *size {
+                        tail.pop_front();
+                    }
+                    tail.push_back(line.to_string());
+                }
+            }
+            Err(e) => {
+                log::error!("failed to read dapp {}: {}", name, e);
+                break;
+            }
+        }
+    }
+}
+
+async fn run_once(config: &Config, spec: &ProcessSpec, status: &ProcessStatus) -> Exit {
+    let args = &spec.command;
+    log::info!("starting {}: {}", spec.name, args.join(" "));
+    let start_failure = |message: String| Exit {
+        success: false,
+        report: ExceptionReport {
+            command: args.to_vec(),
+            ..ExceptionReport::new(DAPP_START_FAILURE, message)
+        },
+    };
+    let command = args.to_vec();
+    let env = process_env(config, spec);
+    let clear_env = spec.clear_env;
+    let cwd = spec.cwd.clone();
+    let capture = config.dapp_capture_output;
+    let limits = spec.limits.clone();
+    let task = tokio::task::spawn_blocking(move || {
+        let mut command_builder = Command::new(&command[0]);
+        command_builder.args(&command[1..]);
+        if clear_env {
+            command_builder.env_clear();
+        }
+        command_builder.envs(env);
+        if let Some(cwd) = cwd {
+            command_builder.current_dir(cwd);
+        }
+        #[cfg(unix)]
+        if limits.has_rlimits() {
+            // SAFETY: the closure only calls setrlimit, which is safe between fork and exec
+            unsafe {
+                command_builder.pre_exec(move || apply_rlimits(&limits));
+            }
+        }
+        #[cfg(not(unix))]
+        if limits.has_rlimits() {
+            log::warn!("resource limits are not supported on this platform, ignoring them");
+        }
+        if capture {
+            command_builder
+                .stdout(Stdio::piped())
+                .stderr(Stdio::piped());
+        }
+        command_builder.spawn()
+    });
+    match task.await {
         Ok(command_result) => match command_result {
-            Ok(mut child) => match child.wait().await {
-                Ok(status) => format!("dapp exited with {}", status),
-                Err(e) => format!("dapp wait failed with {}", e),
-            },
-            Err(e) => format!("dapp failed to start with {}", e),
+            Ok(mut child) => {
+                let started_at = SystemTime::now();
+                let pid = child.id().unwrap_or_default();
+                status.update(|state| {
+                    state.supervised = true;
+                    state.running = true;
+                    state.ready = spec.ready.is_none();
+                    state.pid = child.id();
+                    state.starts += 1;
+                });
+                let stderr_tail = Arc::new(Mutex::new(VecDeque::new()));
+                let peak = Arc::new(Mutex::new(PeakUsage::default()));
+                let sampler = (spec.limits.address_space.is_some()
+                    || spec.limits.open_files.is_some())
+                .then(|| tokio::spawn(sample_usage(pid, peak.clone())));
+                let mut forwarders = Vec::new();
+                if let Some(stdout) = child.stdout.take() {
+                    forwarders.push(tokio::spawn(forward_output(
+                        stdout,
+                        "stdout",
+                        pid,
+                        config.dapp_stdout_level,
+                        None,
+                    )));
+                }
+                if let Some(stderr) = child.stderr.take() {
+                    let tail = (config.dapp_stderr_tail > 0)
+                        .then(|| (stderr_tail.clone(), config.dapp_stderr_tail));
+                    forwarders.push(tokio::spawn(forward_output(
+                        stderr,
+                        "stderr",
+                        pid,
+                        config.dapp_stderr_level,
+                        tail,
+                    )));
+                }
+                // Resolves only if the dapp did not become ready in time
+                let startup = async {
+                    if let Some(probe) = &spec.ready {
+                        if readiness::wait_ready(probe, config.dapp_startup_timeout).await {
+                            log::info!("{} {} is ready", spec.name, pid);
+                            status.update(|state| state.ready = true);
+                        } else {
+                            return;
+                        }
+                    }
+                    std::future::pending().await
+                };
+                let deadline = async {
+                    match spec.limits.wall_clock {
+                        Some(seconds) => tokio::time::sleep(Duration::from_secs(seconds)).await,
+                        None => std::future::pending().await,
+                    }
+                };
+                let mut not_ready = false;
+                let mut deadline_exceeded = false;
+                let result = tokio::select! {
+                    result = child.wait() => result,
+                    _ = deadline => {
+                        log::error!(
+                            "{} {} still running after its {}s deadline, terminating it",
+                            spec.name,
+                            pid,
+                            spec.limits.wall_clock.unwrap_or_default()
+                        );
+                        deadline_exceeded = true;
+                        shutdown::terminate(&mut child, SIGTERM, config.shutdown_grace).await.0
+                    }
+                    _ = startup => {
+                        log::error!(
+                            "{} {} not ready within {:?}, terminating it",
+                            spec.name,
+                            pid,
+                            config.dapp_startup_timeout
+                        );
+                        not_ready = true;
+                        shutdown::terminate(&mut child, SIGTERM, config.shutdown_grace).await.0
+                    }
+                    signal = status.shutdown_signal() => {
+                        log::info!("forwarding signal {} to {} {}", signal, spec.name, pid);
+                        let (result, killed) =
+                            shutdown::terminate(&mut child, signal, config.shutdown_grace).await;
+                        status.update(|state| state.killed = killed);
+                        result
+                    }
+                };
+                if let Some(sampler) = sampler {
+                    sampler.abort();
+                }
+                // Drain what the dapp wrote before exiting, descendants may keep the pipes open
+                let drain = futures::future::join_all(forwarders.iter_mut());
+                if tokio::time::timeout(OUTPUT_DRAIN_TIMEOUT, drain)
+                    .await
+                    .is_err()
+                {
+                    forwarders.iter().for_each(|forwarder| forwarder.abort());
+                }
+                status.update(|state| {
+                    state.running = false;
+                    state.ready = false;
+                    state.pid = None;
+                });
+                let mut report = ExceptionReport {
+                    command: args.to_vec(),
+                    started_at_ms: Some(unix_time_ms(started_at)),
+                    exited_at_ms: Some(unix_time_ms(SystemTime::now())),
+                    stderr_tail: stderr_tail.lock().unwrap().drain(..).collect(),
+                    ..ExceptionReport::new(DAPP_EXIT, "")
+                };
+                if not_ready {
+                    report.kind = DAPP_NOT_READY.to_string();
+                    report.message = format!(
+                        "dapp did not become ready within {:?}",
+                        config.dapp_startup_timeout
+                    );
+                    if let Ok(exit_status) = &result {
+                        report.exit_code = exit_status.code();
+                        report.signal = exit_signal(exit_status);
+                    }
+                    return Exit {
+                        success: false,
+                        report,
+                    };
+                }
+                match result {
+                    Ok(exit_status) => {
+                        report.message = format!("dapp exited with {}", exit_status);
+                        report.exit_code = exit_status.code();
+                        report.signal = exit_signal(&exit_status);
+                        let exceeded = if deadline_exceeded {
+                            spec.limits
+                                .wall_clock
+                                .map(|seconds| ("wall_clock", seconds))
+                        } else {
+                            let peak = *peak.lock().unwrap();
+                            exceeded_limit(&spec.limits, &exit_status, &peak)
+                        };
+                        if let Some((limit, value)) = exceeded {
+                            report.kind = DAPP_LIMIT_EXCEEDED.to_string();
+                            report.message = format!(
+                                "dapp exceeded its {} limit of {}, exited with {}",
+                                limit, value, exit_status
+                            );
+                            report.details.insert("limit".to_string(), limit.into());
+                            report
+                                .details
+                                .insert("limit_value".to_string(), value.into());
+                            return Exit {
+                                success: false,
+                                report,
+                            };
+                        }
+                        Exit {
+                            success: exit_status.success(),
+                            report,
+                        }
+                    }
+                    Err(e) => {
+                        report.message = format!("dapp wait failed with {}", e);
+                        Exit {
+                            success: false,
+                            report,
+                        }
+                    }
+                }
+            }
+            Err(e) => start_failure(format!("dapp failed to start with {}", e)),
         },
-        Err(e) => format!("failed to spawn task with {}", e),
+        Err(e) => start_failure(format!("failed to spawn task with {}", e)),
+    }
+}
+
+/// Execute the dapp command, restarting it according to the configured policy, and throw a
+/// rollup exception once the policy gives up or the dapp never becomes ready
+pub async fn run(
+    config: &Config,
+    rollup: &RollupClient,
+    args: Vec<String>,
+    status: Arc<ProcessStatus>,
+) {
+    supervise(config, rollup, &ProcessSpec::dapp(args, config), &status).await
+}
+
+/// Supervise a single process of the manifest
+async fn supervise(
+    config: &Config,
+    rollup: &RollupClient,
+    spec: &ProcessSpec,
+    status: &ProcessStatus,
+) {
+    let mut restarts: VecDeque<Instant> = VecDeque::new();
+    let mut report = loop {
+        let exit = run_once(config, spec, status).await;
+        status.update(|state| state.last_exit = Some(exit.report.message.clone()));
+        if status.is_shutting_down() {
+            log::info!("{} during shutdown", exit.report.message);
+            return;
+        }
+        if exit.report.kind == DAPP_NOT_READY {
+            break exit.report;
+        }
+        let restart = match spec.restart.unwrap_or(config.dapp_restart) {
+            RestartPolicy::Never => false,
+            RestartPolicy::OnFailure => !exit.success,
+            RestartPolicy::Always => true,
+        };
+        if !restart {
+            break exit.report;
+        }
+        while restarts
+            .front()
+            .is_some_and(|at| at.elapsed() > config.dapp_restart_window)
+        {
+            restarts.pop_front();
+        }
+        if restarts.len() >= config.dapp_max_restarts as usize {
+            let mut report = exit.report;
+            report.message = format!(
+                "{}, giving up after {} restarts within {:?}",
+                report.message,
+                restarts.len(),
+                config.dapp_restart_window
+            );
+            break report;
+        }
+        let backoff = config
+            .dapp_restart_backoff
+            .saturating_mul(1 << restarts.len().min(16));
+        log::warn!("{}, restarting in {:?}", exit.report.message, backoff);
+        tokio::select! {
+            _ = tokio::time::sleep(backoff) => {}
+            _ = status.shutdown_signal() => return,
+        }
+        restarts.push_back(Instant::now());
+        status.update(|state| {
+            state.restarts += 1;
+            state.restarts_in_window = restarts.len() as u32;
+        });
     };
-    log::warn!("throwing exception because {}", message);
-    let exception = Exception {
-        payload: String::from("0x") + &hex::encode(message),
+    status.update(|state| {
+        state.exhausted = true;
+        report.restarts = state.restarts;
+    });
+    if spec.name != DAPP {
+        report
+            .details
+            .insert("process".to_string(), spec.name.clone().into());
+    }
+    throw_exception(rollup, &report).await;
+}
+
+/// Processes started from the manifest, in start order
+#[derive(Debug, Default)]
+pub struct ProcessGroup {
+    processes: Vec<Arc<ProcessStatus>>,
+    /// Signal to forward to the processes once salsa is shutting down
+    shutdown: watch::Sender<Option<i32>>,
+}
+
+impl ProcessGroup {
+    /// Group tracking the status of `processes`, which are not started yet
+    pub fn new(processes: &[ProcessSpec]) -> Self {
+        Self {
+            processes: processes
+                .iter()
+                .map(|process| Arc::new(ProcessStatus::named(&process.name)))
+                .collect(),
+            shutdown: watch::channel(None).0,
+        }
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Arc<ProcessStatus>> {
+        self.processes
+            .iter()
+            .find(|status| status.state.borrow().name == name)
+    }
+
+    /// Status of the process named after the dapp
+    pub fn dapp(&self) -> Option<&Arc<ProcessStatus>> {
+        self.get(DAPP)
+    }
+
+    pub fn snapshot(&self) -> Vec<ProcessState> {
+        self.processes
+            .iter()
+            .map(|status| status.snapshot())
+            .collect()
+    }
+
+    /// Whether every process passed its readiness probe
+    pub fn is_ready(&self) -> bool {
+        self.processes.iter().all(|status| status.is_ready())
+    }
+
+    /// Whether a process had to be killed during shutdown
+    pub fn killed(&self) -> bool {
+        self.processes.iter().any(|status| status.snapshot().killed)
+    }
+
+    /// Stop the processes together, forwarding `signal` to them
+    pub fn shutdown(&self, signal: i32) {
+        self.shutdown.send_replace(Some(signal));
+    }
+
+    async fn shutdown_signal(&self) -> i32 {
+        let mut shutdown = self.shutdown.subscribe();
+        let signal = shutdown
+            .wait_for(Option::is_some)
+            .await
+            .map(|signal| signal.unwrap_or(SIGTERM));
+        match signal {
+            Ok(signal) => signal,
+            Err(_) => std::future::pending().await,
+        }
+    }
+}
+
+/// Start the processes in order, each once its dependencies are ready, and supervise them
+/// until they are all done or salsa is shutting down
+pub async fn run_group(
+    config: &Config,
+    rollup: &RollupClient,
+    processes: Vec<ProcessSpec>,
+    group: Arc<ProcessGroup>,
+) {
+    let mut tasks = Vec::new();
+    'start: for (spec, status) in processes.into_iter().zip(group.processes.clone()) {
+        for dependency in &spec.depends_on {
+            let Some(dependency_status) = group.get(dependency) else {
+                continue;
+            };
+            let ready = tokio::select! {
+                ready = dependency_status.wait_ready() => ready,
+                _ = group.shutdown_signal() => break 'start,
+            };
+            if !ready {
+                log::error!(
+                    "not starting {} because {} never became ready",
+                    spec.name,
+                    dependency
+                );
+                status.update(|state| {
+                    state.exhausted = true;
+                    state.last_exit = Some(format!("{} never became ready", dependency));
+                });
+                continue 'start;
+            }
+        }
+        let config = config.clone();
+        let rollup = rollup.clone();
+        let task_status = status.clone();
+        let task =
+            tokio::spawn(async move { supervise(&config, &rollup, &spec, &task_status).await });
+        tasks.push((status, task));
+    }
+    let signal = tokio::select! {
+        signal = group.shutdown_signal() => signal,
+        _ = futures::future::join_all(tasks.iter_mut().map(|(_, task)| task)) => return,
     };
-    let client = utils::create_client();
-    let body = utils::body_bytes(serde_json::to_string(&exception).unwrap());
-    let req = hyper::Request::builder()
-        .method(hyper::Method::POST)
-        .header(hyper::header::CONTENT_TYPE, "application/json")
-        .uri("http://127.0.0.1:5004/exception")
-        .body(body)
-        .expect("exception request");
-    match client.request(req).await {
+    // Every process gets the signal at once, so the shutdown takes a single grace period
+    for (status, _) in &tasks {
+        status.shutdown(signal);
+    }
+    for (status, task) in tasks {
+        if let Err(e) = task.await {
+            log::error!("supervisor of {} failed: {}", status.snapshot().name, e);
+        }
+    }
+}
+
+async fn throw_exception(rollup: &RollupClient, report: &ExceptionReport) {
+    log::warn!("throwing exception because {}", report.message);
+    let exception = report.to_exception();
+    match rollup.throw_exception(&exception).await {
         Ok(_) => {
             log::debug!("exception successfully thrown {:#?}", exception);
         }
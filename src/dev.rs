@@ -0,0 +1,130 @@
+//! Local GIO backend for `salsa dev`, keeping everything the rollup server would
+//! provide on the local disk.
+
+use std::collections::HashMap;
+use std::fs;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use cid::Cid;
+use sha3::{Digest, Sha3_256};
+
+use crate::config::DevConfig;
+use crate::mock::{block_key, decode, externalized_block_key, not_found, ok};
+use crate::rollup::{
+    GIORequest, GIOResponse, CURRENT_STATE_CID, EXTERNALIZE_STATE, HINT, IPFS_GET_BLOCK,
+    KECCAK256_NAMESPACE, METADATA, SET_STATE_CID,
+};
+
+/// State of a fresh dev environment, the CID of an empty directory
+const EMPTY_STATE_CID: &str = "QmUNLLsPACCz1vLxQVkXqqLX5R1X345qqfHbsf67hvA3Nn";
+
+/// Parse a metadata value, `0x` prefixed hex, a CID or otherwise plain text
+fn metadata_value(value: &str) -> Vec<u8> {
+    if let Some(hex_value) = value.strip_prefix("0x") {
+        if let Ok(bytes) = hex::decode(hex_value) {
+            return bytes;
+        }
+    }
+    match Cid::try_from(value) {
+        Ok(cid) => cid.to_bytes(),
+        Err(_) => value.as_bytes().to_vec(),
+    }
+}
+
+#[derive(Debug)]
+pub struct LocalBackend {
+    state_cid_file: PathBuf,
+    blocks_dir: PathBuf,
+    preimages_dir: Option<PathBuf>,
+    metadata: HashMap<Vec<u8>, Vec<u8>>,
+    // Serializes access to the state CID file
+    lock: Mutex<()>,
+}
+
+impl LocalBackend {
+    pub fn open(config: &DevConfig) -> std::io::Result<Self> {
+        let blocks_dir = config.data_dir.join("blocks");
+        fs::create_dir_all(&blocks_dir)?;
+        let mut metadata = HashMap::new();
+        if let Some(path) = &config.metadata_file {
+            let entries: HashMap<String, String> = serde_json::from_slice(&fs::read(path)?)?;
+            for (name, value) in entries {
+                let key = Sha3_256::digest(name.as_bytes()).to_vec();
+                metadata.insert(key, metadata_value(&value));
+            }
+        }
+        Ok(Self {
+            state_cid_file: config.data_dir.join("state_cid"),
+            blocks_dir,
+            preimages_dir: config.preimages_dir.clone(),
+            metadata,
+            lock: Mutex::new(()),
+        })
+    }
+
+    /// Answer a GIO request the way the rollup server would, missing entries are answered
+    /// with a not found response code. Reads and writes the disk, so it blocks.
+    pub fn handle_gio(&self, request: &GIORequest) -> std::io::Result<GIOResponse> {
+        let payload = decode(&request.payload);
+        let response = match request.domain {
+            CURRENT_STATE_CID => {
+                let _guard = self.lock.lock().unwrap();
+                let cid = read_optional(&self.state_cid_file)?
+                    .map(|cid| String::from_utf8_lossy(&cid).into_owned())
+                    .unwrap_or_else(|| EMPTY_STATE_CID.to_string());
+                ok(Cid::try_from(cid.trim()).map_err(invalid_data)?.to_bytes())
+            }
+            SET_STATE_CID => {
+                let cid = Cid::try_from(payload).map_err(invalid_data)?;
+                let _guard = self.lock.lock().unwrap();
+                fs::write(&self.state_cid_file, cid.to_string())?;
+                ok([])
+            }
+            METADATA => self.metadata.get(&payload).map_or_else(not_found, ok),
+            KECCAK256_NAMESPACE => match self.preimage_path(&payload) {
+                Some(path) => read_optional(&path)?.map_or_else(not_found, ok),
+                None => not_found(),
+            },
+            EXTERNALIZE_STATE => {
+                let key = externalized_block_key(&payload);
+                fs::write(self.blocks_dir.join(hex::encode(key)), payload)?;
+                ok([])
+            }
+            IPFS_GET_BLOCK => match block_key(&payload) {
+                Some(key) => read_optional(&self.blocks_dir.join(hex::encode(key)))?
+                    .map_or_else(not_found, ok),
+                None => not_found(),
+            },
+            HINT => {
+                log::info!("dapp hint: {}", String::from_utf8_lossy(&payload));
+                ok([])
+            }
+            _ => not_found(),
+        };
+        Ok(response)
+    }
+
+    /// File holding the preimage of `data_id`, rejecting ids that would escape the directory
+    fn preimage_path(&self, data_id: &[u8]) -> Option<PathBuf> {
+        let name = std::str::from_utf8(data_id).ok()?;
+        if name.is_empty() || name.contains(['/', '\\']) || name.starts_with('.') {
+            return None;
+        }
+        Some(self.preimages_dir.as_ref()?.join(name))
+    }
+}
+
+/// Content of the file at `path`, `None` when there is no such file
+fn read_optional(path: &Path) -> std::io::Result<Option<Vec<u8>>> {
+    match fs::read(path) {
+        Ok(content) => Ok(Some(content)),
+        Err(e) if e.kind() == ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+fn invalid_data(e: cid::Error) -> std::io::Error {
+    std::io::Error::new(ErrorKind::InvalidData, e)
+}
@@ -1,466 +1,780 @@
+use std::fmt;
 use std::sync::Arc;
-
-use crate::config::Config;
-use crate::rollup::{GIORequest, GIOResponse};
+use std::time::Instant;
+
+use crate::auth;
+use crate::config::{Config, CorsConfig, Listener};
+use crate::dapp_process::{ProcessGroup, ProcessState};
+use crate::ipfs::Ipfs;
+use crate::metrics::metrics;
+use crate::operations::{self, ServiceError};
+use crate::rollup::client::{RollupClient, RollupError};
+use crate::rollup::ExceptionReport;
+use crate::rpc;
+use crate::telemetry::{self, REQUEST_ID_HEADER};
 use crate::utils;
-use actix_web::http::header::ContentType;
+use actix_cors::Cors;
+use actix_web::body::{EitherBody, MessageBody};
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::http::header::{ContentType, HeaderName, HeaderValue, CONTENT_LENGTH};
+use actix_web::http::{Method, Uri};
+use actix_web::middleware::{from_fn, Condition, Logger, Next};
 use actix_web::web;
-use actix_web::web::{Bytes, BytesMut};
-use actix_web::{middleware::Logger, App, HttpResponse, HttpServer};
-use cid::Cid;
-use futures::StreamExt;
-use ipfs_api_backend_hyper::{IpfsApi, IpfsClient, TryFromUri};
-use sha3::{Digest, Sha3_256};
-use std::io::Cursor;
-use tokio::sync::Notify;
-
-const CURRENT_STATE_CID: u16 = 0x20;
-const SET_STATE_CID: u16 = 0x21;
-const METADATA: u16 = 0x22;
-const KECCAK256_NAMESPACE: u16 = 0x23;
-const EXTERNALIZE_STATE: u16 = 0x24;
-const IPFS_GET_BLOCK: u16 = 0x25;
-const HINT: u16 = 0x26;
+use actix_web::web::Bytes;
+use actix_web::{delete, get, head, post, put, route};
+use actix_web::{App, HttpRequest, HttpResponse, HttpServer};
+use serde::Serialize;
+use tracing::Instrument;
+use utoipa::{OpenApi, ToSchema};
+
+/// Declare the routes of the http service once, so that every route the server registers is
+/// also in its OpenAPI document
+macro_rules! routes {
+    ($($handler:ident),* $(,)?) => {
+        /// OpenAPI document of the http service, generated from the handlers
+        #[derive(OpenApi)]
+        #[openapi(
+            info(
+                title = "salsa",
+                description = "State management and IPFS access for a dapp running in a Cartesi machine",
+                license(name = "Apache-2.0", identifier = "Apache-2.0")
+            ),
+            paths($($handler),*),
+            components(schemas(
+                ProcessState,
+                ExceptionReport,
+                Health,
+                Readiness,
+                Version,
+                PayloadTooLarge
+            ))
+        )]
+        pub struct ApiDoc;
+
+        fn services(config: &mut web::ServiceConfig) {
+            $(config.service($handler);)*
+        }
+    };
+}
+
+routes!(
+    open_state,
+    commit_state,
+    delete_state,
+    set_state,
+    get_state,
+    get_metadata,
+    get_data,
+    ipfs_get,
+    ipfs_put,
+    ipfs_has,
+    hint,
+    get_app,
+    dapp_status,
+    processes_status,
+    health,
+    ready,
+    version,
+    raise_exception,
+    json_rpc,
+    openapi_json,
+    prometheus_metrics,
+);
 
 /// Create new instance of http server
 pub fn create_server(config: &Config) -> std::io::Result<actix_server::Server> {
-    let server = HttpServer::new(move || {
+    create_server_with_processes(
+        config,
+        Arc::new(RollupClient::new(config)?),
+        Arc::new(ProcessGroup::default()),
+    )
+}
+
+/// Create new instance of http server reporting the status of the supervised processes, sharing
+/// `rollup` with the rest of salsa so that there is a single GIO cache, trace and dev backend
+pub fn create_server_with_processes(
+    config: &Config,
+    rollup: Arc<RollupClient>,
+    processes: Arc<ProcessGroup>,
+) -> std::io::Result<actix_server::Server> {
+    let rollup = web::Data::from(rollup);
+    let ipfs = web::Data::new(Ipfs::new(config)?);
+    let processes = web::Data::from(processes);
+    let config_data = web::Data::new(config.clone());
+    if let Some(cors) = &config.cors {
+        check_cors(cors)?;
+    }
+    let mut server = HttpServer::new(move || {
         App::new()
+            .app_data(rollup.clone())
+            .app_data(ipfs.clone())
+            .app_data(processes.clone())
+            .app_data(config_data.clone())
+            // Bodies are bounded per route by `limit_body`
+            .app_data(web::PayloadConfig::new(usize::MAX))
+            .app_data(web::JsonConfig::default().limit(usize::MAX))
+            .wrap(from_fn(auth::authenticate))
+            .wrap(from_fn(limit_body))
+            .wrap(from_fn(record_metrics))
             .wrap(Logger::default())
-            .service(open_state)
-            .service(commit_state)
-            .service(delete_state)
-            .service(set_state)
-            .service(get_state)
-            .service(get_metadata)
-            .service(get_data)
-            .service(ipfs_get)
-            .service(ipfs_put)
-            .service(ipfs_has)
-            .service(hint)
-            .service(get_app)
+            // Outside of the authentication, preflight requests carry no credentials
+            .wrap(Condition::new(
+                config_data.cors.is_some(),
+                cors(&config_data),
+            ))
+            .wrap(from_fn(trace_request))
+            .configure(services)
     })
-    .bind((config.http_address.as_str(), config.http_port))?
-    .run();
-    Ok(server)
+    .disable_signals()
+    // Whole seconds, rounded up so that a sub-second grace is not cut to no grace at all
+    .shutdown_timeout(config.shutdown_grace.as_millis().div_ceil(1000) as u64);
+    for listener in config.listeners() {
+        server = match listener {
+            Listener::Tcp { address, port } => server.bind((address.as_str(), port))?,
+            #[cfg(unix)]
+            Listener::Unix(path) => {
+                remove_stale_socket(&path)?;
+                server.bind_uds(path)?
+            }
+            #[cfg(not(unix))]
+            Listener::Unix(path) => {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::Unsupported,
+                    format!("unix socket {:?} is not supported on this platform", path),
+                ))
+            }
+        };
+    }
+    Ok(server.run())
 }
 
-/// Create and run new instance of http server
-pub async fn run(config: &Config, server_ready: Arc<Notify>) -> std::io::Result<()> {
-    log::info!("starting http dispatcher http service!");
-    let server = create_server(config)?;
-    server_ready.notify_one();
-    server.await
+/// Remove the socket left at `path` by a previous run, binding fails while it exists
+#[cfg(unix)]
+fn remove_stale_socket(path: &std::path::Path) -> std::io::Result<()> {
+    use std::os::unix::fs::FileTypeExt;
+    match std::fs::symlink_metadata(path) {
+        Ok(meta) if meta.file_type().is_socket() => std::fs::remove_file(path),
+        _ => Ok(()),
+    }
 }
 
-// Deletes state with a particular key
-#[actix_web::delete("/delete_state/{key}")]
-async fn delete_state(key: web::Path<String>) -> HttpResponse {
-    let client = IpfsClient::from_str("http://127.0.0.1:5001").unwrap();
-    let key_path = format!("/state/{}", key.into_inner());
-    match client.files_rm(&key_path, true).await {
-        Ok(_) => HttpResponse::Ok().finish(),
-        Err(_) => HttpResponse::InternalServerError().finish(),
+/// Refuse the method, header and origin names that the CORS middleware would fail on in each
+/// worker, and a wildcard origin that other origins would silently narrow
+fn check_cors(cors: &CorsConfig) -> std::io::Result<()> {
+    let invalid = |e: String| std::io::Error::new(std::io::ErrorKind::InvalidInput, e);
+    for method in &cors.allowed_methods {
+        Method::from_bytes(method.as_bytes())
+            .map_err(|_| invalid(format!("cors method '{}' is not valid", method)))?;
+    }
+    for header in &cors.allowed_headers {
+        HeaderName::from_bytes(header.as_bytes())
+            .map_err(|_| invalid(format!("cors header '{}' is not valid", header)))?;
     }
+    if cors.allowed_origins.is_empty() {
+        return Err(invalid("cors allows no origin".to_string()));
+    }
+    if cors.allowed_origins.len() > 1 && cors.allowed_origins.iter().any(|o| o == "*") {
+        return Err(invalid(
+            "cors origin '*' cannot be combined with other origins".to_string(),
+        ));
+    }
+    for origin in cors.allowed_origins.iter().filter(|o| *o != "*") {
+        let uri = HeaderValue::from_str(origin)
+            .ok()
+            .and_then(|_| origin.parse::<Uri>().ok());
+        let is_origin = uri.is_some_and(|uri| {
+            matches!(uri.scheme_str(), Some("http" | "https"))
+                && uri.authority().is_some_and(|authority| {
+                    *origin == format!("{}://{}", uri.scheme_str().unwrap(), authority)
+                })
+        });
+        if !is_origin {
+            return Err(invalid(format!(
+                "cors origin '{}' is not a http or https origin",
+                origin
+            )));
+        }
+    }
+    Ok(())
 }
 
-// Sets state with a particular key
-#[actix_web::post("/set_state/{key}")]
-async fn set_state(key: web::Path<String>, body: Bytes) -> HttpResponse {
-    let client = IpfsClient::from_str("http://127.0.0.1:5001").unwrap();
-    let base_path = "/state";
-    let _ = client.files_mkdir(base_path, true).await;
-    let key_path = format!("{}/{}", base_path, key.into_inner());
-
-    let reader = Cursor::new(body);
-
-    match client.files_write(&key_path, true, true, reader).await {
-        Ok(_) => HttpResponse::Ok().finish(),
-        Err(_) => HttpResponse::InternalServerError().finish(),
+/// Cross-origin policy configured in `cors`, a default one that is not used when it is unset
+fn cors(config: &Config) -> Cors {
+    let Some(settings) = &config.cors else {
+        return Cors::default();
+    };
+    let mut cors = Cors::default()
+        .allowed_methods(settings.allowed_methods.iter().map(String::as_str))
+        .allowed_headers(settings.allowed_headers.iter().map(String::as_str))
+        .expose_headers([CONTENT_LENGTH.as_str(), REQUEST_ID_HEADER])
+        .max_age(settings.max_age);
+    for origin in &settings.allowed_origins {
+        cors = if origin == "*" {
+            cors.allow_any_origin()
+        } else {
+            cors.allowed_origin(origin)
+        };
     }
+    cors
 }
 
-// Receives state with a particular key
-#[actix_web::get("/get_state/{key}")]
-async fn get_state(key: web::Path<String>) -> HttpResponse {
-    let client = IpfsClient::from_str("http://127.0.0.1:5001").unwrap();
-    let key_path = format!("/state/{}", key.into_inner());
-
-    let stream = client.files_read(&key_path);
-    let result = stream
-        .fold(BytesMut::new(), |mut acc, item| async move {
-            match item {
-                Ok(chunk) => {
-                    acc.extend_from_slice(&chunk);
-                    acc
-                }
-                Err(_) => acc,
+/// Run the request in an `http_request` span so the GIO and IPFS calls it makes can be told
+/// apart, its id is sent back in the `x-request-id` header
+async fn trace_request(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, actix_web::Error> {
+    let request_id = req
+        .headers()
+        .get(REQUEST_ID_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .filter(|value| !value.is_empty())
+        .map_or_else(telemetry::request_id, str::to_string);
+    let span = tracing::info_span!(
+        "http_request",
+        otel.kind = "server",
+        request_id = %request_id,
+        method = %req.method(),
+        route = %req.match_pattern().unwrap_or_else(|| req.path().to_string()),
+        status = tracing::field::Empty,
+        error = tracing::field::Empty,
+    );
+    let mut response = next.call(req).instrument(span.clone()).await?;
+    let status = response.status();
+    span.record("status", status.as_u16());
+    if status.is_server_error() {
+        span.record("error", status.canonical_reason().unwrap_or("server error"));
+    }
+    if let Ok(value) = HeaderValue::from_str(&request_id) {
+        response
+            .headers_mut()
+            .insert(HeaderName::from_static(REQUEST_ID_HEADER), value);
+    }
+    Ok(response)
+}
+
+/// Answer with a 413 when the body is larger than the limit of its route, before the handler
+/// buffers it
+async fn limit_body(
+    mut req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<EitherBody<impl MessageBody>>, actix_web::Error> {
+    let limit = match (req.match_pattern(), req.app_data::<web::Data<Config>>()) {
+        (Some(pattern), Some(config)) => config.body_limit(&pattern),
+        _ => None,
+    };
+    let Some((route, limit)) = limit else {
+        return Ok(next.call(req).await?.map_into_left_body());
+    };
+    let declared = req
+        .headers()
+        .get(CONTENT_LENGTH)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.parse::<usize>().ok());
+    let within_limit = match declared {
+        Some(length) => length <= limit,
+        // Chunked bodies are read until they go over the limit
+        None => match utils::read_body(&mut req, limit).await? {
+            Some(body) => {
+                utils::restore_body(&mut req, body);
+                true
             }
-        })
-        .await;
+            None => false,
+        },
+    };
+    if within_limit {
+        return Ok(next.call(req).await?.map_into_left_body());
+    }
+    let what = format!("request body of {}", route);
+    log::error!(
+        "failed to handle {} request: {} is over {} bytes",
+        route,
+        what,
+        limit
+    );
+    let response = payload_too_large(&what, limit);
+    Ok(req.into_response(response).map_into_right_body())
+}
 
-    HttpResponse::Ok()
-        .content_type("application/octet-stream")
-        .body(result.freeze())
-}
-
-#[actix_web::get("/get_app")]
-async fn get_app() -> HttpResponse {
-    let mut hasher = Sha3_256::new();
-    hasher.update("lambada-app".as_bytes());
-    let hash_result = hasher.finalize();
-    let gio_request = GIORequest {
-        domain: METADATA,
-        payload: format!("0x{}", hex::encode(hash_result)),
+/// Count requests and their latency by route pattern, so paths with keys share a series
+async fn record_metrics(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, actix_web::Error> {
+    let started = Instant::now();
+    let method = req.method().to_string();
+    let route = req
+        .match_pattern()
+        .unwrap_or_else(|| "unmatched".to_string());
+    let response = next.call(req).await;
+    let status = match &response {
+        Ok(response) => response.status(),
+        Err(e) => e.as_response_error().status_code(),
     };
+    metrics().observe_http(&method, &route, status.as_u16(), started.elapsed());
+    response
+}
 
-    let client = utils::create_client();
-
-    //Request for getting state_cid from rollup_http_server qio request
-    let req = hyper::Request::builder()
-        .method(hyper::Method::POST)
-        .header(hyper::header::CONTENT_TYPE, "application/json")
-        .uri("http://127.0.0.1:5004/gio")
-        .body(utils::body_bytes(
-            serde_json::to_string(&gio_request).unwrap(),
-        ))
-        .expect("gio request");
-    match client.request(req).await {
-        Ok(gio_response) => {
-            let gio_response = serde_json::from_slice::<GIOResponse>(
-                &utils::response_to_bytes(gio_response)
-                    .await
-                    .expect("error get response from rollup_http_server qio request"),
-            )
-            .unwrap();
-
-            let endpoint = "http://127.0.0.1:5001".to_string();
-            let cid = Cid::try_from(hex::decode(&gio_response.response[2..]).unwrap()).unwrap();
-
-            // Updates new state using cid received from rollup_http_server qio request
-            let client = IpfsClient::from_str(&endpoint).unwrap();
-
-            client.files_rm("/app", true).await.unwrap();
-            client
-                .files_cp(&("/ipfs/".to_string() + &cid.to_string()), "/app")
-                .await
-                .unwrap();
-
-            HttpResponse::Ok()
-                .append_header(ContentType::octet_stream())
-                .body(cid.to_string())
-        }
-        Err(e) => {
-            log::error!("failed to handle open_state request: {}", e);
-            HttpResponse::BadRequest().body(format!("Failed to handle open_state request: {}", e))
+/// Body of a 413 answer
+#[derive(Serialize, ToSchema)]
+struct PayloadTooLarge {
+    error: String,
+    /// Largest accepted size in bytes
+    limit: usize,
+}
+
+pub(crate) fn payload_too_large(what: &str, limit: usize) -> HttpResponse {
+    HttpResponse::PayloadTooLarge().json(PayloadTooLarge {
+        error: format!("{} exceeds the limit of {} bytes", what, limit),
+        limit,
+    })
+}
+
+impl ServiceError {
+    /// Log the failure and turn it into the response for `request`
+    fn respond(self, request: &str) -> HttpResponse {
+        log::error!("failed to handle {} request: {}", request, self);
+        if let ServiceError::Rollup(RollupError::PayloadTooLarge { limit, .. }) = self {
+            return payload_too_large(&format!("GIO payload of {}", request), limit);
         }
+        HttpResponse::build(self.status())
+            .body(format!("Failed to handle {} request: {}", request, self))
     }
 }
 
-// Receives state with a particular key
-#[actix_web::get("/open_state")]
-async fn open_state() -> HttpResponse {
-    let gio_request = GIORequest {
-        domain: CURRENT_STATE_CID,
-        payload: "0x".to_string(),
-    };
+fn octet_stream(body: impl Into<Bytes>) -> HttpResponse {
+    HttpResponse::Ok()
+        .append_header(ContentType::octet_stream())
+        .body(body.into())
+}
 
-    let client = utils::create_client();
-
-    //Request for getting state_cid from rollup_http_server qio request
-    let req = hyper::Request::builder()
-        .method(hyper::Method::POST)
-        .header(hyper::header::CONTENT_TYPE, "application/json")
-        .uri("http://127.0.0.1:5004/gio")
-        .body(utils::body_bytes(
-            serde_json::to_string(&gio_request).unwrap(),
-        ))
-        .expect("gio request");
-    match client.request(req).await {
-        Ok(gio_response) => {
-            let gio_response = serde_json::from_slice::<GIOResponse>(
-                &utils::response_to_bytes(gio_response)
-                    .await
-                    .expect("error get response from rollup_http_server qio request"),
-            )
-            .unwrap();
-
-            let endpoint = "http://127.0.0.1:5001".to_string();
-            let client = IpfsClient::from_str(&endpoint).unwrap();
-            let cid = Cid::try_from(hex::decode(&gio_response.response[2..]).unwrap()).unwrap();
-
-            // Updates new state using cid received from rollup_http_server qio request
-            client
-                .files_cp(&("/ipfs/".to_string() + &cid.to_string()), "/state-new")
-                .await
-                .unwrap();
-            client.files_rm("/state-new/previous", true).await.unwrap();
-            client
-                .files_cp(
-                    &("/ipfs/".to_string() + &cid.to_string()),
-                    "/state-new/previous",
-                )
-                .await
-                .unwrap();
-            client.files_rm("/state", true).await.unwrap();
-            client.files_mv("/state-new", "/state").await.unwrap();
-
-            HttpResponse::Ok()
-                .append_header(ContentType::octet_stream())
-                .body(Vec::new())
-        }
-        Err(e) => {
-            log::error!("failed to handle open_state request: {}", e);
-            HttpResponse::BadRequest().body(format!("Failed to handle open_state request: {}", e))
-        }
+// Deletes state with a particular key
+#[utoipa::path(
+    params(("key" = String, Path, description = "Key of the state entry")),
+    responses(
+        (status = 200, description = "State entry deleted"),
+        (status = 500, description = "IPFS failed to delete the entry"),
+        (status = 504, description = "IPFS did not answer in time"),
+    )
+)]
+#[delete("/delete_state/{key}")]
+async fn delete_state(ipfs: web::Data<Ipfs>, key: web::Path<String>) -> HttpResponse {
+    match operations::delete_state(&ipfs, &key).await {
+        Ok(()) => HttpResponse::Ok().finish(),
+        Err(e) => HttpResponse::build(e.status()).finish(),
     }
 }
 
-#[actix_web::get("/commit_state")]
-async fn commit_state() -> HttpResponse {
-    let endpoint = "http://127.0.0.1:5001".to_string();
-    let client = IpfsClient::from_str(&endpoint).unwrap();
-    let cid = client.files_stat("/state").await.unwrap().hash;
-    let cid = Cid::try_from(cid).unwrap();
-    let cid_bytes = cid.to_bytes();
+// Sets state with a particular key
+#[utoipa::path(
+    params(("key" = String, Path, description = "Key of the state entry")),
+    request_body(content = String, content_type = "application/octet-stream"),
+    responses(
+        (status = 200, description = "State entry written"),
+        (status = 413, description = "The value is over the `set_state` body limit", body = PayloadTooLarge),
+        (status = 500, description = "IPFS failed to write the entry"),
+        (status = 504, description = "IPFS did not answer in time"),
+    )
+)]
+#[post("/set_state/{key}")]
+async fn set_state(ipfs: web::Data<Ipfs>, key: web::Path<String>, body: Bytes) -> HttpResponse {
+    match operations::write_state(&ipfs, &key, body).await {
+        Ok(()) => HttpResponse::Ok().finish(),
+        Err(e) => HttpResponse::build(e.status()).finish(),
+    }
+}
 
-    let gio_request = GIORequest {
-        domain: SET_STATE_CID,
-        payload: format!("0x{}", hex::encode(cid_bytes)),
-    };
-    let client = utils::create_client();
-
-    // rollup_http_server gio request with cid received from /state
-    let req = hyper::Request::builder()
-        .method(hyper::Method::POST)
-        .header(hyper::header::CONTENT_TYPE, "application/json")
-        .uri("http://127.0.0.1:5004/gio")
-        .body(utils::body_bytes(
-            serde_json::to_string(&gio_request).unwrap(),
-        ))
-        .expect("gio request");
-
-    match client.request(req).await {
-        Ok(gio_response) => {
-            let _gio_response = serde_json::from_slice::<GIOResponse>(
-                &utils::response_to_bytes(gio_response)
-                    .await
-                    .expect("error get response from rollup_http_server qio request"),
-            )
-            .unwrap();
-
-            HttpResponse::Ok()
-                .append_header(ContentType::octet_stream())
-                .body(Vec::new())
-        }
-        Err(e) => {
-            log::error!("failed to handle commit_state request: {}", e);
-            HttpResponse::BadRequest().body(format!("Failed to handle commit_state request: {}", e))
-        }
+// Receives state with a particular key
+#[utoipa::path(
+    params(("key" = String, Path, description = "Key of the state entry")),
+    responses(
+        (status = 200, description = "Content of the state entry", body = String, content_type = "application/octet-stream"),
+        (status = 500, description = "IPFS failed to read the entry", body = String),
+        (status = 504, description = "IPFS did not answer in time", body = String),
+    )
+)]
+#[route("/get_state/{key}", method = "GET", method = "HEAD")]
+async fn get_state(ipfs: web::Data<Ipfs>, key: web::Path<String>) -> HttpResponse {
+    match operations::read_state(&ipfs, &key).await {
+        Ok(result) => octet_stream(result),
+        Err(e) => e.respond("get_state"),
     }
 }
 
-#[actix_web::get("/metadata/{text}")]
-async fn get_metadata(text: web::Path<String>) -> HttpResponse {
-    let mut hasher = Sha3_256::new();
-    hasher.update(text.as_bytes());
-    let hash_result = hasher.finalize();
+#[utoipa::path(
+    responses(
+        (status = 200, description = "CID of the app copied to /app", body = String, content_type = "application/octet-stream"),
+        (status = 400, description = "The app CID or manifest is not valid", body = String),
+        (status = 500, description = "IPFS failed to copy the app", body = String),
+        (status = 504, description = "The rollup server or IPFS did not answer in time", body = String),
+    )
+)]
+#[get("/get_app")]
+async fn get_app(
+    config: web::Data<Config>,
+    rollup: web::Data<RollupClient>,
+    ipfs: web::Data<Ipfs>,
+) -> HttpResponse {
+    match operations::get_app(&config, &rollup, &ipfs).await {
+        Ok(cid) => octet_stream(cid.to_string()),
+        Err(e) => e.respond("get_app"),
+    }
+}
 
-    let gio_request = GIORequest {
-        domain: METADATA,
-        payload: format!("0x{}", hex::encode(hash_result)),
-    };
-    let client = utils::create_client();
-
-    let req = hyper::Request::builder()
-        .method(hyper::Method::POST)
-        .header(hyper::header::CONTENT_TYPE, "application/json")
-        .uri("http://127.0.0.1:5004/gio")
-        .body(utils::body_bytes(
-            serde_json::to_string(&gio_request).unwrap(),
-        ))
-        .expect("gio request");
-
-    match client.request(req).await {
-        Ok(gio_response) => {
-            let gio_response = serde_json::from_slice::<GIOResponse>(
-                &utils::response_to_bytes(gio_response)
-                    .await
-                    .expect("error get response from rollup_http_server qio request"),
-            )
-            .unwrap();
-
-            HttpResponse::Ok()
-                .append_header(ContentType::octet_stream())
-                .body(hex::decode(&gio_response.response[2..]).unwrap())
-        }
-        Err(e) => {
-            log::error!("failed to handle get_metadata request: {}", e);
-            HttpResponse::BadRequest().body(format!("Failed to handle get_metadata request: {}", e))
-        }
+// Receives state with a particular key
+#[utoipa::path(
+    responses(
+        (status = 200, description = "Current state copied to /state"),
+        (status = 400, description = "The rollup server failed or answered an invalid CID", body = String),
+        (status = 500, description = "IPFS failed to copy the state", body = String),
+        (status = 504, description = "The rollup server or IPFS did not answer in time", body = String),
+    )
+)]
+#[get("/open_state")]
+async fn open_state(rollup: web::Data<RollupClient>, ipfs: web::Data<Ipfs>) -> HttpResponse {
+    match operations::open_state(&rollup, &ipfs).await {
+        Ok(()) => octet_stream(Vec::new()),
+        Err(e) => e.respond("open_state"),
     }
 }
 
-#[actix_web::put("/ipfs/put/{cid}")]
-async fn ipfs_put(content: Bytes, _cid: web::Path<String>) -> HttpResponse {
-    let gio_request = GIORequest {
-        domain: EXTERNALIZE_STATE,
-        payload: format!("0x{}", hex::encode(content)),
-    };
-    let client = utils::create_client();
-
-    let req = hyper::Request::builder()
-        .method(hyper::Method::POST)
-        .header(hyper::header::CONTENT_TYPE, "application/json")
-        .uri("http://127.0.0.1:5004/gio")
-        .body(utils::body_bytes(
-            serde_json::to_string(&gio_request).unwrap(),
-        ))
-        .expect("gio request");
-
-    match client.request(req).await {
-        Ok(gio_response) => {
-            let gio_response = serde_json::from_slice::<GIOResponse>(
-                &utils::response_to_bytes(gio_response)
-                    .await
-                    .expect("error get response from rollup_http_server gio request"),
-            )
-            .unwrap();
-
-            HttpResponse::Ok()
-                .append_header(ContentType::octet_stream())
-                .body(hex::decode(&gio_response.response[2..]).unwrap())
-        }
-        Err(e) => {
-            log::error!("failed to handle ipfs_put request: {}", e);
-            HttpResponse::BadRequest().body(format!("Failed to handle ipfs_put request: {}", e))
-        }
+#[utoipa::path(
+    responses(
+        (status = 200, description = "CID of /state set as the new state"),
+        (status = 400, description = "The rollup server failed to set the state", body = String),
+        (status = 500, description = "IPFS failed to hash /state", body = String),
+        (status = 504, description = "The rollup server or IPFS did not answer in time", body = String),
+    )
+)]
+#[get("/commit_state")]
+async fn commit_state(rollup: web::Data<RollupClient>, ipfs: web::Data<Ipfs>) -> HttpResponse {
+    match operations::commit_state(&rollup, &ipfs).await {
+        Ok(()) => octet_stream(Vec::new()),
+        Err(e) => e.respond("commit_state"),
+    }
+}
+
+#[utoipa::path(
+    params(("text" = String, Path, description = "Name of the metadata entry, looked up by its sha3-256")),
+    responses(
+        (status = 200, description = "Value of the metadata entry", body = String, content_type = "application/octet-stream"),
+        (status = 400, description = "The rollup server failed", body = String),
+        (status = 504, description = "The rollup server did not answer in time", body = String),
+    )
+)]
+#[get("/metadata/{text}")]
+async fn get_metadata(rollup: web::Data<RollupClient>, text: web::Path<String>) -> HttpResponse {
+    match operations::metadata(&rollup, &text).await {
+        Ok(data) => octet_stream(data),
+        Err(e) => e.respond("get_metadata"),
     }
 }
 
-#[actix_web::head("/ipfs/has/{cid}")]
+#[utoipa::path(
+    params(("cid" = String, Path, description = "CID of the block, not checked")),
+    request_body(content = String, content_type = "application/octet-stream"),
+    responses(
+        (status = 200, description = "Block externalized", body = String, content_type = "application/octet-stream"),
+        (status = 413, description = "The block is over the `ipfs_put` body limit or does not fit in a GIO payload", body = PayloadTooLarge),
+        (status = 400, description = "The rollup server failed", body = String),
+        (status = 504, description = "The rollup server did not answer in time", body = String),
+    )
+)]
+#[put("/ipfs/put/{cid}")]
+async fn ipfs_put(
+    rollup: web::Data<RollupClient>,
+    content: Bytes,
+    _cid: web::Path<String>,
+) -> HttpResponse {
+    match operations::ipfs_put(&rollup, content).await {
+        Ok(data) => octet_stream(data),
+        Err(e) => e.respond("ipfs_put"),
+    }
+}
+
+#[utoipa::path(
+    params(("cid" = String, Path, description = "CID of the block")),
+    responses(
+        (status = 200, description = "Always answered, every block can be fetched through the rollup server"),
+    )
+)]
+#[head("/ipfs/has/{cid}")]
 async fn ipfs_has(_cid: web::Path<String>) -> HttpResponse {
     HttpResponse::new(actix_web::http::StatusCode::from_u16(200).unwrap())
 }
 
-#[actix_web::get("/ipfs/get/{cid}")]
-async fn ipfs_get(cid: web::Path<String>) -> HttpResponse {
-    let cid = cid.into_inner();
-    let gio_request = GIORequest {
-        domain: IPFS_GET_BLOCK,
-        payload: format!("0x{}", hex::encode(Cid::try_from(cid).unwrap().to_bytes())),
-    };
-    let client = utils::create_client();
-
-    let req = hyper::Request::builder()
-        .method(hyper::Method::POST)
-        .header(hyper::header::CONTENT_TYPE, "application/json")
-        .uri("http://127.0.0.1:5004/gio")
-        .body(utils::body_bytes(
-            serde_json::to_string(&gio_request).unwrap(),
-        ))
-        .expect("gio request");
-
-    match client.request(req).await {
-        Ok(gio_response) => {
-            let gio_response = serde_json::from_slice::<GIOResponse>(
-                &utils::response_to_bytes(gio_response)
-                    .await
-                    .expect("error get response from rollup_http_server gio request"),
-            )
-            .unwrap();
-
-            HttpResponse::Ok()
-                .append_header(ContentType::octet_stream())
-                .body(hex::decode(&gio_response.response[2..]).unwrap())
-        }
-        Err(e) => {
-            log::error!("failed to handle ipfs_put request: {}", e);
-            HttpResponse::BadRequest().body(format!("Failed to handle ipfs_put request: {}", e))
-        }
+#[utoipa::path(
+    params(("cid" = String, Path, description = "CID of the block")),
+    responses(
+        (status = 200, description = "Content of the block", body = String, content_type = "application/octet-stream"),
+        (status = 400, description = "The CID is not valid or the rollup server failed", body = String),
+        (status = 504, description = "The rollup server did not answer in time", body = String),
+    )
+)]
+#[route("/ipfs/get/{cid}", method = "GET", method = "HEAD")]
+async fn ipfs_get(rollup: web::Data<RollupClient>, cid: web::Path<String>) -> HttpResponse {
+    match operations::ipfs_get(&rollup, &cid).await {
+        Ok(data) => octet_stream(data),
+        Err(e) => e.respond("ipfs_get"),
     }
 }
 
-#[actix_web::get("/get_data/{namespace}/{data_id}")]
-async fn get_data(path: web::Path<(String, String)>) -> HttpResponse {
+#[utoipa::path(
+    params(
+        ("namespace" = String, Path, description = "Namespace of the preimage, only keccak256 is supported"),
+        ("data_id" = String, Path, description = "Identifier of the preimage in the namespace"),
+    ),
+    responses(
+        (status = 200, description = "Preimage of the data id", body = String, content_type = "application/octet-stream"),
+        (status = 400, description = "The namespace is not supported or the rollup server failed", body = String),
+        (status = 504, description = "The rollup server did not answer in time", body = String),
+    )
+)]
+#[get("/get_data/{namespace}/{data_id}")]
+async fn get_data(
+    rollup: web::Data<RollupClient>,
+    path: web::Path<(String, String)>,
+) -> HttpResponse {
     let (namespace, data_id) = path.into_inner();
-    let data_id_as_bytes = data_id.as_bytes();
+    match operations::data(&rollup, &namespace, &data_id).await {
+        Ok(data) => octet_stream(data),
+        Err(e) => e.respond("get_data"),
+    }
+}
 
-    if !namespace.eq("keccak256") {
-        log::error!("failed to handle get_data request: namespace should be keccak256");
-        return HttpResponse::BadRequest()
-            .body("Failed to handle get_data request: namespace should be keccak256");
+#[utoipa::path(
+    params(("what" = String, Path, description = "Hint passed on to the rollup server")),
+    responses(
+        (status = 200, description = "Answer of the rollup server", body = String, content_type = "application/octet-stream"),
+        (status = 400, description = "The rollup server failed", body = String),
+        (status = 413, description = "The hint is over the `hint` limit", body = PayloadTooLarge),
+        (status = 504, description = "The rollup server did not answer in time", body = String),
+    )
+)]
+#[get("/hint/{what}")]
+async fn hint(
+    rollup: web::Data<RollupClient>,
+    config: web::Data<Config>,
+    what: web::Path<String>,
+) -> HttpResponse {
+    if what.len() > config.hint_limit() {
+        log::error!(
+            "failed to handle hint request: hint is over {} bytes",
+            config.hint_limit()
+        );
+        return payload_too_large("hint", config.hint_limit());
     }
+    match operations::hint(&rollup, &what).await {
+        Ok(data) => octet_stream(data),
+        Err(e) => e.respond("hint"),
+    }
+}
 
-    let gio_request = GIORequest {
-        domain: KECCAK256_NAMESPACE,
-        payload: format!("0x{}", hex::encode(data_id_as_bytes)),
-    };
-    let client = utils::create_client();
-
-    let req = hyper::Request::builder()
-        .method(hyper::Method::POST)
-        .header(hyper::header::CONTENT_TYPE, "application/json")
-        .uri("http://127.0.0.1:5004/gio")
-        .body(utils::body_bytes(
-            serde_json::to_string(&gio_request).unwrap(),
-        ))
-        .expect("gio request");
-
-    match client.request(req).await {
-        Ok(gio_response) => {
-            let gio_response = serde_json::from_slice::<GIOResponse>(
-                &utils::response_to_bytes(gio_response)
-                    .await
-                    .expect("error get response from rollup_http_server qio request"),
-            )
-            .unwrap();
-
-            HttpResponse::Ok()
-                .append_header(ContentType::octet_stream())
-                .body(hex::decode(&gio_response.response[2..]).unwrap())
-        }
-        Err(e) => {
-            log::error!("failed to handle get_data request: {}", e);
-            HttpResponse::BadRequest().body(format!("Failed to handle get_data request: {}", e))
+// Reports whether the dapp is running and how often it was restarted
+#[utoipa::path(
+    responses(
+        (status = 200, description = "Status of the dapp", body = ProcessState),
+    )
+)]
+#[get("/dapp/status")]
+async fn dapp_status(processes: web::Data<ProcessGroup>) -> HttpResponse {
+    let state = processes
+        .dapp()
+        .map(|dapp| dapp.snapshot())
+        .unwrap_or_default();
+    HttpResponse::Ok().json(state)
+}
+
+// Reports the status of every supervised process, in start order
+#[utoipa::path(
+    responses(
+        (status = 200, description = "Status of every supervised process", body = Vec<ProcessState>),
+    )
+)]
+#[get("/processes")]
+async fn processes_status(processes: web::Data<ProcessGroup>) -> HttpResponse {
+    HttpResponse::Ok().json(processes.snapshot())
+}
+
+#[derive(Serialize, ToSchema)]
+struct Health {
+    status: String,
+}
+
+// Answers 200 as long as salsa serves requests
+#[utoipa::path(
+    responses(
+        (status = 200, description = "Salsa is alive", body = Health),
+    )
+)]
+#[get("/health")]
+async fn health() -> HttpResponse {
+    HttpResponse::Ok().json(Health {
+        status: "ok".to_string(),
+    })
+}
+
+/// Outcome of probing one backend
+#[derive(Serialize, ToSchema)]
+struct BackendStatus {
+    reachable: bool,
+    error: Option<String>,
+}
+
+impl<E: fmt::Display> From<Result<(), E>> for BackendStatus {
+    fn from(result: Result<(), E>) -> Self {
+        Self {
+            reachable: result.is_ok(),
+            error: result.err().map(|e| e.to_string()),
         }
     }
 }
 
-#[actix_web::get("/hint/{what}")]
-async fn hint(what: web::Path<String>) -> HttpResponse {
-    let what = what.into_inner();
-    let gio_request = GIORequest {
-        domain: HINT,
-        payload: format!("0x{}", hex::encode(what)),
+#[derive(Serialize, ToSchema)]
+struct Readiness {
+    ready: bool,
+    rollup: BackendStatus,
+    ipfs: BackendStatus,
+    processes: Vec<ProcessState>,
+}
+
+// Answers 200 once the backends are reachable and every process passed its readiness probe
+#[utoipa::path(
+    responses(
+        (status = 200, description = "Salsa, its backends and every process are ready", body = Readiness),
+        (status = 503, description = "A backend is unreachable or a process is not ready yet", body = Readiness),
+    )
+)]
+#[get("/ready")]
+async fn ready(
+    config: web::Data<Config>,
+    rollup: web::Data<RollupClient>,
+    ipfs: web::Data<Ipfs>,
+    processes: web::Data<ProcessGroup>,
+) -> HttpResponse {
+    let (rollup, ipfs) = tokio::join!(
+        rollup.probe(config.probe_timeout),
+        ipfs.probe(config.probe_timeout)
+    );
+    let readiness = Readiness {
+        ready: rollup.is_ok() && ipfs.is_ok() && processes.is_ready(),
+        rollup: rollup.into(),
+        ipfs: ipfs.into(),
+        processes: processes.snapshot(),
     };
-    let client = utils::create_client();
-
-    let req = hyper::Request::builder()
-        .method(hyper::Method::POST)
-        .header(hyper::header::CONTENT_TYPE, "application/json")
-        .uri("http://127.0.0.1:5004/gio")
-        .body(utils::body_bytes(
-            serde_json::to_string(&gio_request).unwrap(),
-        ))
-        .expect("gio request");
-
-    match client.request(req).await {
-        Ok(gio_response) => {
-            let gio_response = serde_json::from_slice::<GIOResponse>(
-                &utils::response_to_bytes(gio_response)
-                    .await
-                    .expect("error get response from rollup_http_server gio request"),
-            )
-            .unwrap();
-
-            HttpResponse::Ok()
-                .append_header(ContentType::octet_stream())
-                .body(hex::decode(&gio_response.response[2..]).unwrap())
-        }
-        Err(e) => {
-            log::error!("failed to handle hint request: {}", e);
-            HttpResponse::BadRequest().body(format!("Failed to handle hint request: {}", e))
-        }
+    if readiness.ready {
+        HttpResponse::Ok().json(readiness)
+    } else {
+        HttpResponse::ServiceUnavailable().json(readiness)
     }
 }
+
+#[derive(Serialize, ToSchema)]
+struct Version {
+    version: String,
+    /// Commit salsa was built from, `unknown` outside of a git checkout
+    git_commit: String,
+    /// Cargo features salsa was built with
+    features: Vec<String>,
+    /// Effective configuration, durations in milliseconds
+    #[schema(value_type = Object)]
+    config: serde_json::Value,
+}
+
+// Reports what salsa was built from and how it is configured
+#[utoipa::path(
+    responses(
+        (status = 200, description = "Build and configuration of salsa", body = Version),
+    )
+)]
+#[get("/version")]
+async fn version(config: web::Data<Config>) -> HttpResponse {
+    let features = env!("SALSA_FEATURES");
+    HttpResponse::Ok().json(Version {
+        version: env!("CARGO_PKG_VERSION").to_string(),
+        git_commit: env!("SALSA_GIT_COMMIT").to_string(),
+        features: features
+            .split(',')
+            .filter(|feature| !feature.is_empty())
+            .map(String::from)
+            .collect(),
+        config: serde_json::to_value(config.as_ref()).unwrap_or_default(),
+    })
+}
+
+// Lets the dapp raise a typed exception through the same path as dapp_process
+#[utoipa::path(
+    request_body = ExceptionReport,
+    responses(
+        (status = 200, description = "Exception thrown"),
+        (status = 413, description = "The report is over the `exception` body limit", body = PayloadTooLarge),
+        (status = 400, description = "The kind is empty or the rollup server failed", body = String),
+        (status = 504, description = "The rollup server did not answer in time", body = String),
+    )
+)]
+#[post("/exception")]
+async fn raise_exception(
+    rollup: web::Data<RollupClient>,
+    report: web::Json<ExceptionReport>,
+) -> HttpResponse {
+    if report.kind.is_empty() {
+        log::error!("failed to handle exception request: kind should not be empty");
+        return HttpResponse::BadRequest()
+            .body("Failed to handle exception request: kind should not be empty");
+    }
+    log::warn!("dapp raised {} exception: {}", report.kind, report.message);
+    match rollup.throw_exception(&report.to_exception()).await {
+        Ok(()) => HttpResponse::Ok().finish(),
+        Err(e) => ServiceError::from(e).respond("exception"),
+    }
+}
+
+// Runs JSON-RPC 2.0 calls mirroring the REST routes, binary values are base64 encoded
+#[utoipa::path(
+    request_body(content = Object, description = "Request or batch of requests", content_type = "application/json"),
+    responses(
+        (status = 200, description = "Response, or array of responses for a batch", body = Object),
+        (status = 204, description = "Only notifications were sent"),
+        (status = 413, description = "The request is over the `rpc` body limit", body = PayloadTooLarge),
+    )
+)]
+#[post("/rpc")]
+async fn json_rpc(
+    req: HttpRequest,
+    body: Bytes,
+    config: web::Data<Config>,
+    rollup: web::Data<RollupClient>,
+    ipfs: web::Data<Ipfs>,
+) -> HttpResponse {
+    match rpc::handle(&req, &body, &config, &rollup, &ipfs).await {
+        Some(response) => HttpResponse::Ok().json(response),
+        None => HttpResponse::NoContent().finish(),
+    }
+}
+
+// Describes the routes of salsa, this document
+#[utoipa::path(
+    responses(
+        (status = 200, description = "OpenAPI description of salsa", body = Object),
+    )
+)]
+#[get("/openapi.json")]
+async fn openapi_json() -> HttpResponse {
+    HttpResponse::Ok().json(ApiDoc::openapi())
+}
+
+// Exposes the metrics of salsa in the Prometheus text format
+#[utoipa::path(
+    responses(
+        (status = 200, description = "Metrics in the Prometheus text format", body = String, content_type = "text/plain; version=0.0.4"),
+    )
+)]
+#[get("/metrics")]
+async fn prometheus_metrics(
+    rollup: web::Data<RollupClient>,
+    processes: web::Data<ProcessGroup>,
+) -> HttpResponse {
+    HttpResponse::Ok()
+        .content_type("text/plain; version=0.0.4")
+        .body(metrics().render(rollup.cache_stats(), &processes.snapshot()))
+}
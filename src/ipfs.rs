@@ -0,0 +1,172 @@
+use std::fmt;
+use std::future::Future;
+use std::io::Cursor;
+use std::time::{Duration, Instant};
+
+use actix_web::web::{Bytes, BytesMut};
+use futures::StreamExt;
+use ipfs_api_backend_hyper::{request, IpfsApi, IpfsClient, TryFromUri};
+use tracing::Instrument;
+
+use crate::config::Config;
+use crate::metrics::metrics;
+
+#[derive(Debug)]
+pub enum IpfsError {
+    /// The IPFS daemon did not answer within the configured timeout
+    Timeout(Duration),
+    /// The IPFS daemon answered with an error
+    Api(String),
+}
+
+impl IpfsError {
+    pub fn is_timeout(&self) -> bool {
+        matches!(self, IpfsError::Timeout(_))
+    }
+}
+
+impl fmt::Display for IpfsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            IpfsError::Timeout(timeout) => write!(f, "ipfs did not answer within {:?}", timeout),
+            IpfsError::Api(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for IpfsError {}
+
+/// MFS operations against the IPFS daemon, each bounded by the configured timeout
+#[derive(Clone)]
+pub struct Ipfs {
+    client: IpfsClient,
+    timeout: Duration,
+}
+
+impl Ipfs {
+    pub fn new(config: &Config) -> std::io::Result<Self> {
+        let client = IpfsClient::from_str(&config.ipfs_url).map_err(|e| {
+            std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                format!("ipfs url '{}' is not valid: {}", config.ipfs_url, e),
+            )
+        })?;
+        Ok(Self {
+            client,
+            timeout: config.ipfs_timeout,
+        })
+    }
+
+    /// Run `call` within the timeout, in an `ipfs` span naming the operation and MFS path
+    async fn bounded<T, E, F>(&self, operation: &str, path: &str, call: F) -> Result<T, IpfsError>
+    where
+        E: fmt::Display,
+        F: Future<Output = Result<T, E>>,
+    {
+        let started = Instant::now();
+        let span = tracing::info_span!(
+            "ipfs",
+            otel.kind = "client",
+            operation,
+            path,
+            bytes = tracing::field::Empty,
+            error = tracing::field::Empty,
+        );
+        let result = match tokio::time::timeout(self.timeout, call.instrument(span.clone())).await {
+            Ok(result) => result.map_err(|e| IpfsError::Api(e.to_string())),
+            Err(_) => Err(IpfsError::Timeout(self.timeout)),
+        };
+        if let Err(e) = &result {
+            span.record("error", tracing::field::display(e));
+        }
+        metrics().observe_ipfs(operation, result.is_ok(), started.elapsed());
+        result
+    }
+
+    /// Check that the daemon answers within `timeout`, shorter than the one of MFS operations
+    pub async fn probe(&self, timeout: Duration) -> Result<(), IpfsError> {
+        match tokio::time::timeout(timeout, self.client.version()).await {
+            Ok(result) => result
+                .map(|_| ())
+                .map_err(|e| IpfsError::Api(e.to_string())),
+            Err(_) => Err(IpfsError::Timeout(timeout)),
+        }
+    }
+
+    pub async fn files_rm(&self, path: &str) -> Result<(), IpfsError> {
+        self.bounded("files_rm", path, self.client.files_rm(path, true))
+            .await
+    }
+
+    pub async fn files_mkdir(&self, path: &str) -> Result<(), IpfsError> {
+        self.bounded("files_mkdir", path, self.client.files_mkdir(path, true))
+            .await
+    }
+
+    pub async fn files_cp(&self, path: &str, dest: &str) -> Result<(), IpfsError> {
+        self.bounded("files_cp", dest, self.client.files_cp(path, dest))
+            .await
+    }
+
+    pub async fn files_mv(&self, path: &str, dest: &str) -> Result<(), IpfsError> {
+        self.bounded("files_mv", dest, self.client.files_mv(path, dest))
+            .await
+    }
+
+    /// Returns the CID of the MFS entry at `path`
+    pub async fn files_stat_hash(&self, path: &str) -> Result<String, IpfsError> {
+        self.bounded("files_stat", path, self.client.files_stat(path))
+            .await
+            .map(|stat| stat.hash)
+    }
+
+    /// Names of the entries of the MFS directory at `path`, with whether each is a directory
+    pub async fn files_ls(&self, path: &str) -> Result<Vec<(String, bool)>, IpfsError> {
+        let request = request::FilesLs {
+            path: Some(path),
+            long: Some(true),
+            ..Default::default()
+        };
+        self.bounded("files_ls", path, self.client.files_ls_with_options(request))
+            .await
+            .map(|ls| {
+                ls.entries
+                    .into_iter()
+                    .map(|entry| (entry.name, entry.typ == 1))
+                    .collect()
+            })
+    }
+
+    pub async fn files_write(&self, path: &str, data: Bytes) -> Result<(), IpfsError> {
+        let bytes = data.len();
+        self.bounded("files_write", path, async {
+            tracing::Span::current().record("bytes", bytes);
+            self.client
+                .files_write(path, true, true, Cursor::new(data))
+                .await
+        })
+        .await
+    }
+
+    /// Reads the whole file, chunks that fail to arrive are skipped
+    pub async fn files_read(&self, path: &str) -> Result<Bytes, IpfsError> {
+        let read = self
+            .client
+            .files_read(path)
+            .fold(BytesMut::new(), |mut acc, item| async move {
+                match item {
+                    Ok(chunk) => {
+                        acc.extend_from_slice(&chunk);
+                        acc
+                    }
+                    Err(_) => acc,
+                }
+            });
+        self.bounded("files_read", path, async {
+            let data = read.await.freeze();
+            tracing::Span::current().record("bytes", data.len());
+            Ok::<_, IpfsError>(data)
+        })
+        .await
+    }
+}
@@ -1,5 +1,17 @@
+pub mod app;
+pub mod auth;
 pub mod config;
+pub mod dapp_process;
+pub mod dev;
 pub mod http_service;
+pub mod ipfs;
+pub mod manifest;
+pub mod metrics;
+pub mod mock;
+pub mod operations;
+pub mod readiness;
 pub mod rollup;
-pub mod dapp_process;
-pub mod utils;
\ No newline at end of file
+pub mod rpc;
+pub mod shutdown;
+pub mod telemetry;
+pub mod utils;
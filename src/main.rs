@@ -1,36 +1,340 @@
+use std::fmt;
 use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::str::FromStr;
 use std::sync::Arc;
+use std::time::Duration;
 
 use getopts::{Options, ParsingStyle};
-use salsa::{config::Config, dapp_process, http_service};
+use log::LevelFilter;
+use salsa::auth::RouteGroup;
+use salsa::config::{AuthMethod, Config, CorsConfig, DevConfig, ReadinessProbe, Secret};
+use salsa::dapp_process::{self, ProcessGroup};
+use salsa::manifest::{ProcessManifest, ProcessSpec};
+use salsa::rollup::client::RollupClient;
+use salsa::telemetry::{self, LogFormat};
+use salsa::{app, http_service, shutdown};
 use tokio::sync::Notify;
 
 fn print_usage(program: &str, opts: Options) {
     let brief = format!(
-        "Usage: {} [options] <command> [args]\n\
+        "Usage: {0} [options] <command> [args]\n\
+        \x20      {0} dev [options] <command> [args]\n\
+        \x20      {0} config print [options]\n\
         \n\
-        Where command and args start the DApp.",
+        Where command and args start the DApp. The dev subcommand answers GIO\n\
+        requests from the local disk instead of the rollup server. The config\n\
+        print subcommand shows the configuration merged from the defaults, the\n\
+        --config file, the SALSA_* environment variables and the options.",
         program
     );
     print!("{}", opts.usage(&brief));
 }
 
+/// Value of the option `name` parsed as `T`, `None` when it is not given
+fn parse_opt<T>(matches: &getopts::Matches, name: &str) -> std::io::Result<Option<T>>
+where
+    T: FromStr,
+    T::Err: fmt::Display,
+{
+    let Some(value) = matches.opt_str(name) else {
+        return Ok(None);
+    };
+    match value.parse() {
+        Ok(value) => Ok(Some(value)),
+        Err(e) => {
+            let message = format!("--{} '{}' is not valid: {}", name, value, e);
+            eprintln!("error parsing arguments: {}", message);
+            Err(std::io::Error::new(ErrorKind::InvalidInput, message))
+        }
+    }
+}
+
 #[actix_web::main]
-async fn main() -> std::io::Result<()> {
-    let args: Vec<String> = std::env::args().collect();
+async fn main() -> std::io::Result<ExitCode> {
+    let mut args: Vec<String> = std::env::args().collect();
     let program = args[0].clone();
+    let dev_mode = args.get(1).is_some_and(|command| command == "dev");
+    if dev_mode {
+        args.remove(1);
+    }
+    let print_config = args.get(1).is_some_and(|command| command == "config");
+    if print_config {
+        if args.get(2).map(String::as_str) != Some("print") {
+            eprintln!("expected config print");
+            return Err(std::io::Error::new(
+                ErrorKind::InvalidInput,
+                "expected config print",
+            ));
+        }
+        args.drain(1..3);
+    }
     // Process command line arguments
     let mut opts = Options::new();
     opts.parsing_style(ParsingStyle::StopAtFirstFree);
     opts.optflag("h", "help", "show this help message and exit");
     opts.optopt(
+        "",
+        "config",
+        "TOML config file, overridden by SALSA_* variables and options",
+        "",
+    );
+    opts.optmulti(
         "",
         "address",
-        "Address to listen (default: 127.0.0.1:5005)",
+        "Address to listen, host:port, [ipv6]:port or unix:/path, repeat to listen on \
+        several (default: 127.0.0.1:5005)",
         "",
     );
     opts.optopt("", "dapp", "Dapp address (default: 127.0.0.1:5005)", "");
+    opts.optopt(
+        "",
+        "rollup-url",
+        "Rollup http server url (default: http://127.0.0.1:5004)",
+        "",
+    );
+    opts.optopt(
+        "",
+        "ipfs-url",
+        "IPFS daemon api url (default: http://127.0.0.1:5001)",
+        "",
+    );
+    opts.optopt(
+        "",
+        "gio-timeout",
+        "Timeout of a single GIO request in milliseconds (default: 30000)",
+        "",
+    );
+    opts.optopt(
+        "",
+        "gio-retries",
+        "Retries of GIO requests on idempotent domains (default: 3)",
+        "",
+    );
+    opts.optopt(
+        "",
+        "ipfs-timeout",
+        "Timeout of a single IPFS call in milliseconds (default: 30000)",
+        "",
+    );
+    opts.optflag(
+        "",
+        "no-gio-cache",
+        "disable the cache of GIO responses on immutable domains",
+    );
+    opts.optopt(
+        "",
+        "gio-cache-entries",
+        "Maximum number of cached GIO responses (default: 1024)",
+        "",
+    );
+    opts.optopt(
+        "",
+        "gio-cache-bytes",
+        "Maximum size in bytes of the cached GIO responses (default: 67108864)",
+        "",
+    );
+    opts.optopt(
+        "",
+        "gio-record",
+        "Append every GIO request and response to this JSONL trace file",
+        "",
+    );
+    opts.optopt(
+        "",
+        "gio-replay",
+        "Answer GIO requests from this JSONL trace file instead of the rollup server",
+        "",
+    );
+    opts.optopt(
+        "",
+        "dev-dir",
+        "Directory of the dev mode state and blockstore (default: .salsa)",
+        "",
+    );
+    opts.optopt(
+        "",
+        "dev-metadata",
+        "JSON file with the metadata served in dev mode",
+        "",
+    );
+    opts.optopt(
+        "",
+        "dev-preimages",
+        "Directory with the get_data preimages served in dev mode",
+        "",
+    );
+    opts.optopt(
+        "",
+        "restart",
+        "When to restart the dapp after it exits: never, on-failure or always (default: never)",
+        "",
+    );
+    opts.optopt(
+        "",
+        "max-restarts",
+        "Maximum dapp restarts within the restart window (default: 5)",
+        "",
+    );
+    opts.optopt(
+        "",
+        "restart-window",
+        "Window in seconds in which dapp restarts are counted (default: 60)",
+        "",
+    );
+    opts.optopt(
+        "",
+        "restart-backoff",
+        "Delay in milliseconds before restarting the dapp, doubled on every restart (default: 1000)",
+        "",
+    );
+    opts.optflag(
+        "",
+        "no-capture-output",
+        "let the dapp inherit stdout and stderr instead of logging them",
+    );
+    opts.optopt(
+        "",
+        "dapp-stdout-level",
+        "Log level of the dapp stdout lines (default: info)",
+        "",
+    );
+    opts.optopt(
+        "",
+        "dapp-stderr-level",
+        "Log level of the dapp stderr lines (default: warn)",
+        "",
+    );
+    opts.optopt(
+        "",
+        "stderr-tail",
+        "Number of dapp stderr lines reported when it exits (default: 20)",
+        "",
+    );
+    opts.optopt(
+        "",
+        "shutdown-grace",
+        "Seconds given to requests and the dapp to finish on SIGTERM or SIGINT (default: 10)",
+        "",
+    );
+    opts.optopt(
+        "",
+        "limit-address-space",
+        "Maximum virtual memory of the dapp in bytes",
+        "",
+    );
+    opts.optopt(
+        "",
+        "limit-open-files",
+        "Maximum number of file descriptors the dapp can open",
+        "",
+    );
+    opts.optopt(
+        "",
+        "limit-cpu-time",
+        "Maximum CPU time of the dapp in seconds",
+        "",
+    );
+    opts.optopt(
+        "",
+        "deadline",
+        "Seconds after which the dapp is terminated",
+        "",
+    );
+    opts.optmulti(
+        "",
+        "env",
+        "Variable added to the dapp environment",
+        "KEY=VALUE",
+    );
+    opts.optopt("", "workdir", "Working directory of the dapp", "DIR");
+    opts.optflag(
+        "",
+        "clear-env",
+        "Start the dapp with only the variables set by salsa and --env",
+    );
+    opts.optflag(
+        "",
+        "from-app-cid",
+        "Fetch the app from the lambada-app metadata and start the dapp it defines",
+    );
+    opts.optopt(
+        "",
+        "app-cid",
+        "CID the app has to be, pinning its manifest and every other file",
+        "CID",
+    );
+    opts.optopt(
+        "",
+        "app-dir",
+        "Directory the app is fetched into (default: salsa-app in the temp directory)",
+        "DIR",
+    );
+    opts.optopt(
+        "",
+        "processes",
+        "TOML or JSON manifest of processes started before the dapp",
+        "",
+    );
+    opts.optopt(
+        "",
+        "ready-url",
+        "Dapp is ready once this url answers a GET with a success status",
+        "",
+    );
+    opts.optopt(
+        "",
+        "ready-tcp",
+        "Dapp is ready once this address accepts TCP connections",
+        "",
+    );
+    opts.optopt("", "ready-file", "Dapp is ready once this file exists", "");
+    opts.optopt(
+        "",
+        "ready-timeout",
+        "Seconds the dapp has to become ready before an exception is thrown (default: 60)",
+        "",
+    );
+    opts.optopt(
+        "",
+        "probe-timeout",
+        "Milliseconds the readiness endpoint waits for each backend (default: 1000)",
+        "",
+    );
+    opts.optmulti(
+        "",
+        "auth-token",
+        "Require this bearer token on a route group, GROUP=TOKEN with GROUP one of \
+        read_state, mutate_state or gio",
+        "",
+    );
+    opts.optmulti(
+        "",
+        "auth-hmac",
+        "Require requests to a route group signed with this secret, GROUP=SECRET",
+        "",
+    );
+    opts.optmulti(
+        "",
+        "cors-origin",
+        "Answer cross-origin requests from browsers on this origin, * for any, repeat to \
+        allow several",
+        "",
+    );
     opts.optflag("", "verbose", "print more info about application execution");
+    opts.optopt(
+        "",
+        "log-format",
+        "Format of the log lines, text or json (default: text)",
+        "",
+    );
+    opts.optopt(
+        "",
+        "trace-file",
+        "Append the finished tracing spans to this file in the OTLP/JSON format",
+        "",
+    );
     let matches = match opts.parse(&args[1..]) {
         Ok(m) => m,
         Err(e) => {
@@ -40,60 +344,355 @@ async fn main() -> std::io::Result<()> {
     };
     if matches.opt_present("h") {
         print_usage(&program, opts);
-        return Ok(());
+        return Ok(ExitCode::SUCCESS);
     }
 
-    // Set log level of application
-    let mut log_level = "info";
+    // Create config, the options override the file and the environment
+    let config_file = matches.opt_str("config").map(PathBuf::from);
+    let mut http_config = match Config::load(config_file.as_deref(), std::env::vars()) {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("error loading the configuration: {}", e);
+            return Err(e);
+        }
+    };
     if matches.opt_present("verbose") {
-        log_level = "debug";
+        http_config.log_level = LevelFilter::Debug;
+    }
+    if let Some(format) = matches.opt_str("log-format") {
+        http_config.log_format = match format.parse::<LogFormat>() {
+            Ok(format) => format,
+            Err(e) => {
+                eprintln!("error parsing arguments: {}", &e);
+                return Err(std::io::Error::new(ErrorKind::InvalidInput, e));
+            }
+        };
+    }
+    if let Some(path) = matches.opt_str("trace-file") {
+        http_config.trace_file = Some(PathBuf::from(path));
+    }
+    let addresses = matches.opt_strs("address");
+    if !addresses.is_empty() {
+        http_config.listen = match addresses.iter().map(|address| address.parse()).collect() {
+            Ok(listeners) => listeners,
+            Err(e) => {
+                eprintln!("{}", e);
+                return Err(std::io::Error::new(ErrorKind::InvalidInput, e));
+            }
+        };
+    }
+    if let Some(url) = matches.opt_str("rollup-url") {
+        http_config.rollup_url = url;
+    }
+    if let Some(url) = matches.opt_str("ipfs-url") {
+        http_config.ipfs_url = url;
+    }
+    if let Some(timeout) = parse_opt::<u64>(&matches, "gio-timeout")? {
+        http_config.gio_timeout = Duration::from_millis(timeout);
+    }
+    if let Some(retries) = parse_opt(&matches, "gio-retries")? {
+        http_config.gio_retries = retries;
+    }
+    if matches.opt_present("no-gio-cache") {
+        http_config.gio_cache_enabled = false;
+    }
+    if let Some(entries) = parse_opt(&matches, "gio-cache-entries")? {
+        http_config.gio_cache_entries = entries;
+    }
+    if let Some(bytes) = parse_opt(&matches, "gio-cache-bytes")? {
+        http_config.gio_cache_bytes = bytes;
+    }
+    if matches.opt_present("gio-record") && matches.opt_present("gio-replay") {
+        eprintln!("--gio-record and --gio-replay can not be used together");
+        return Err(std::io::Error::new(
+            ErrorKind::InvalidInput,
+            "--gio-record and --gio-replay can not be used together",
+        ));
+    }
+    if let Some(path) = matches.opt_str("gio-record") {
+        http_config.gio_record = Some(PathBuf::from(path));
+        http_config.gio_replay = None;
+    }
+    if let Some(path) = matches.opt_str("gio-replay") {
+        http_config.gio_replay = Some(PathBuf::from(path));
+        http_config.gio_record = None;
+    }
+    if dev_mode {
+        let dev = http_config.dev.get_or_insert_with(DevConfig::default);
+        if let Some(dir) = matches.opt_str("dev-dir") {
+            dev.data_dir = PathBuf::from(dir);
+        }
+        if let Some(path) = matches.opt_str("dev-metadata") {
+            dev.metadata_file = Some(PathBuf::from(path));
+        }
+        if let Some(path) = matches.opt_str("dev-preimages") {
+            dev.preimages_dir = Some(PathBuf::from(path));
+        }
+    }
+    if let Some(policy) = matches.opt_str("restart") {
+        http_config.dapp_restart = match policy.parse() {
+            Ok(policy) => policy,
+            Err(e) => {
+                eprintln!("{}", e);
+                return Err(std::io::Error::new(ErrorKind::InvalidInput, e));
+            }
+        };
+    }
+    if let Some(restarts) = parse_opt(&matches, "max-restarts")? {
+        http_config.dapp_max_restarts = restarts;
+    }
+    if let Some(window) = parse_opt::<u64>(&matches, "restart-window")? {
+        http_config.dapp_restart_window = Duration::from_secs(window);
+    }
+    if let Some(backoff) = parse_opt::<u64>(&matches, "restart-backoff")? {
+        http_config.dapp_restart_backoff = Duration::from_millis(backoff);
+    }
+    if matches.opt_present("no-capture-output") {
+        http_config.dapp_capture_output = false;
+    }
+    if let Some(level) = parse_opt(&matches, "dapp-stdout-level")? {
+        http_config.dapp_stdout_level = level;
+    }
+    if let Some(level) = parse_opt(&matches, "dapp-stderr-level")? {
+        http_config.dapp_stderr_level = level;
+    }
+    if let Some(lines) = parse_opt(&matches, "stderr-tail")? {
+        http_config.dapp_stderr_tail = lines;
+    }
+    if let Some(grace) = parse_opt::<u64>(&matches, "shutdown-grace")? {
+        http_config.shutdown_grace = Duration::from_secs(grace);
+    }
+    let probes = [
+        matches.opt_str("ready-url").map(ReadinessProbe::Http),
+        matches.opt_str("ready-tcp").map(ReadinessProbe::Tcp),
+        matches
+            .opt_str("ready-file")
+            .map(|path| ReadinessProbe::File(PathBuf::from(path))),
+    ];
+    let mut probes = probes.into_iter().flatten();
+    if let Some(probe) = probes.next() {
+        http_config.dapp_readiness = Some(probe);
+    }
+    if probes.next().is_some() {
+        eprintln!("only one of --ready-url, --ready-tcp and --ready-file can be used");
+        return Err(std::io::Error::new(
+            ErrorKind::InvalidInput,
+            "only one of --ready-url, --ready-tcp and --ready-file can be used",
+        ));
+    }
+    if let Some(timeout) = parse_opt::<u64>(&matches, "ready-timeout")? {
+        http_config.dapp_startup_timeout = Duration::from_secs(timeout);
+    }
+    if let Some(bytes) = parse_opt(&matches, "limit-address-space")? {
+        http_config.dapp_limits.address_space = Some(bytes);
+    }
+    if let Some(files) = parse_opt(&matches, "limit-open-files")? {
+        http_config.dapp_limits.open_files = Some(files);
+    }
+    if let Some(seconds) = parse_opt(&matches, "limit-cpu-time")? {
+        http_config.dapp_limits.cpu_time = Some(seconds);
+    }
+    if let Some(seconds) = parse_opt(&matches, "deadline")? {
+        http_config.dapp_limits.wall_clock = Some(seconds);
+    }
+    for entry in matches.opt_strs("env") {
+        match entry.split_once('=') {
+            Some((key, value)) if !key.is_empty() => {
+                http_config
+                    .dapp_env
+                    .insert(key.to_string(), value.to_string());
+            }
+            _ => {
+                eprintln!("env '{}' is not KEY=VALUE", entry);
+                return Err(std::io::Error::new(
+                    ErrorKind::InvalidInput,
+                    format!("env '{}' is not KEY=VALUE", entry),
+                ));
+            }
+        }
+    }
+    if let Some(dir) = matches.opt_str("workdir") {
+        http_config.dapp_workdir = Some(PathBuf::from(dir));
+    }
+    if let Some(cid) = matches.opt_str("app-cid") {
+        http_config.app_cid = Some(cid);
+    }
+    if matches.opt_present("clear-env") {
+        http_config.dapp_clear_env = true;
+    }
+    if let Some(timeout) = parse_opt::<u64>(&matches, "probe-timeout")? {
+        http_config.probe_timeout = Duration::from_millis(timeout);
+    }
+    if let Some(timeout) = parse_opt::<u64>(&matches, "ipfs-timeout")? {
+        http_config.ipfs_timeout = Duration::from_millis(timeout);
+    }
+    let credentials = [
+        ("auth-token", AuthMethod::Bearer as fn(Secret) -> AuthMethod),
+        ("auth-hmac", AuthMethod::Hmac),
+    ];
+    for (option, method) in credentials {
+        for entry in matches.opt_strs(option) {
+            let parsed = entry
+                .split_once('=')
+                .filter(|(_, secret)| !secret.is_empty())
+                .ok_or_else(|| format!("{} '{}' is not GROUP=SECRET", option, entry))
+                .and_then(|(group, secret)| Ok((group.parse::<RouteGroup>()?, secret)));
+            match parsed {
+                Ok((group, secret)) => http_config
+                    .auth
+                    .set_method(group, method(Secret::new(secret))),
+                Err(e) => {
+                    eprintln!("{}", e);
+                    return Err(std::io::Error::new(ErrorKind::InvalidInput, e));
+                }
+            }
+        }
+    }
+    let origins = matches.opt_strs("cors-origin");
+    if !origins.is_empty() {
+        http_config
+            .cors
+            .get_or_insert_with(CorsConfig::default)
+            .allowed_origins = origins;
+    }
+    if print_config {
+        match toml::to_string(&http_config) {
+            Ok(config) => print!("{}", config),
+            Err(e) => return Err(std::io::Error::other(e)),
+        }
+        return Ok(ExitCode::SUCCESS);
     }
-    // Set the global log level, disable timestamp
-    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or(log_level))
-        .format_timestamp(None)
-        .init();
 
+    // Set the global log level, text lines have no timestamp
+    telemetry::init(
+        &http_config.log_level.to_string(),
+        http_config.log_format,
+        http_config.trace_file.as_deref(),
+    )?;
     log::info!("starting http dispatcher service...");
-
-    // Create config
-    let mut http_config = Config::new();
-    {
-        // Parse addresses and ports
-        let address_matches = matches
-            .opt_get_default("address", "127.0.0.1:5005".to_string())
-            .unwrap_or_default();
-        let mut address = address_matches.split(':');
-        http_config.http_address = address.next().expect("address is not valid").to_string();
-        http_config.http_port = address
-            .next()
-            .expect("port is not valid")
-            .to_string()
-            .parse::<u16>()
-            .unwrap();
+    if let Some(file) = &config_file {
+        log::info!("configuration loaded from {:?}", file);
+    }
+    if let Some(dev) = &http_config.dev {
+        log::info!("dev mode, serving gio requests from {:?}", dev.data_dir);
     }
 
+    // Shared by the app fetch, the server and the supervisor, a replayed trace is read once
+    let rollup = Arc::new(RollupClient::new(&http_config)?);
     let server_ready = Arc::new(Notify::new());
+    let mut manifest = match matches.opt_str("processes") {
+        Some(path) => ProcessManifest::load(Path::new(&path))?,
+        None => ProcessManifest::default(),
+    };
+    let dapp = if matches.opt_present("from-app-cid") {
+        if !matches.free.is_empty() {
+            eprintln!("--from-app-cid can not be used with a command");
+            return Err(std::io::Error::new(
+                ErrorKind::InvalidInput,
+                "--from-app-cid can not be used with a command",
+            ));
+        }
+        let dir = matches
+            .opt_str("app-dir")
+            .map(PathBuf::from)
+            .unwrap_or_else(|| std::env::temp_dir().join("salsa-app"));
+        match app::fetch(&http_config, &rollup, &dir).await {
+            Ok(dapp) => Some(dapp),
+            Err(e) => {
+                log::error!("failed to fetch the app: {}", e);
+                return Err(e);
+            }
+        }
+    } else if !matches.free.is_empty() {
+        Some(ProcessSpec::dapp(matches.free, &http_config))
+    } else {
+        None
+    };
+    if let Some(dapp) = dapp {
+        if let Err(e) = manifest.add_dapp(dapp) {
+            eprintln!("{}", e);
+            return Err(std::io::Error::new(ErrorKind::InvalidInput, e));
+        }
+    }
+    let processes = match manifest.start_order() {
+        Ok(processes) => processes,
+        Err(e) => {
+            eprintln!("{}", e);
+            return Err(std::io::Error::new(ErrorKind::InvalidInput, e));
+        }
+    };
+    let dapp_status = Arc::new(ProcessGroup::new(&processes));
 
-    //In another thread, wait until the server is ready and then start the dapp
-    if !matches.free.is_empty() {
+    //In another thread, wait until the server is ready and then start the processes
+    let dapp_task = if !processes.is_empty() {
         let server_ready = server_ready.clone();
-        tokio::spawn(async move {
+        let dapp_config = http_config.clone();
+        let dapp_status = dapp_status.clone();
+        let rollup = rollup.clone();
+        Some(tokio::spawn(async move {
             server_ready.notified().await;
-            dapp_process::run(matches.free).await;
-        });
+            dapp_process::run_group(&dapp_config, &rollup, processes, dapp_status).await;
+        }))
     } else {
         log::warn!("No command provided for dapp_process. Skipping dapp_process execution.");
-    }
+        None
+    };
 
     // Open http service
-    tokio::select! {
-        result = http_service::run(&http_config, server_ready) => {
+    log::info!("starting http dispatcher http service!");
+    let server = http_service::create_server_with_processes(
+        &http_config,
+        rollup.clone(),
+        dapp_status.clone(),
+    )?;
+    let server_handle = server.handle();
+    server_ready.notify_one();
+    let exit_code = tokio::select! {
+        result = server => {
             match result {
-                Ok(_) => log::info!("http service terminated successfully"),
-                Err(e) => log::warn!("http service terminated with error: {}", e),
+                Ok(_) => {
+                    log::info!("http service terminated successfully");
+                    ExitCode::SUCCESS
+                }
+                Err(e) => {
+                    log::warn!("http service terminated with error: {}", e);
+                    ExitCode::FAILURE
+                }
+            }
+        }
+        signal = shutdown::signal() => {
+            let signal = signal?;
+            log::info!(
+                "received signal {}, shutting down within {:?}",
+                signal,
+                http_config.shutdown_grace
+            );
+            // Stop accepting requests and drain the in-flight ones while the dapp terminates
+            dapp_status.shutdown(signal);
+            let dapp_done = async {
+                if let Some(task) = dapp_task {
+                    let _ = task.await;
+                }
+            };
+            tokio::join!(server_handle.stop(true), dapp_done);
+            if dapp_status.killed() {
+                log::warn!("a process had to be killed after the shutdown grace period");
+                ExitCode::FAILURE
+            } else {
+                ExitCode::SUCCESS
             }
         }
+    };
+    // A replay that stops early did not reproduce the recorded run
+    match rollup.replay_remaining() {
+        Some(remaining) if remaining > 0 => {
+            log::warn!(
+                "{} recorded gio exchanges were not replayed before shutting down",
+                remaining
+            );
+        }
+        _ => {}
     }
     log::info!("ending http dispatcher service!");
-    Ok(())
-}
\ No newline at end of file
+    Ok(exit_code)
+}
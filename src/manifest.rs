@@ -0,0 +1,160 @@
+//! Manifest of the processes salsa starts and supervises next to the dapp.
+
+use std::collections::{BTreeMap, HashSet};
+use std::io::{Error, ErrorKind};
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::config::{Config, ReadinessProbe, ResourceLimits, RestartPolicy};
+
+/// Name of the process started from the command given on the command line
+pub const DAPP: &str = "dapp";
+
+/// One process to start, as listed in the manifest
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ProcessSpec {
+    pub name: String,
+    /// Program followed by its arguments
+    pub command: Vec<String>,
+    /// Variables added to the environment, over the ones salsa sets
+    #[serde(default)]
+    pub env: BTreeMap<String, String>,
+    /// Start with an empty environment instead of inheriting salsa's
+    #[serde(default)]
+    pub clear_env: bool,
+    /// Working directory, salsa's one when unset
+    #[serde(default)]
+    pub cwd: Option<PathBuf>,
+    /// Processes that have to be ready before this one is started
+    #[serde(default)]
+    pub depends_on: Vec<String>,
+    /// How to tell that the process is ready, it is ready once started when unset
+    #[serde(default)]
+    pub ready: Option<ReadinessProbe>,
+    /// Restart policy, the configured one when unset
+    #[serde(default)]
+    pub restart: Option<RestartPolicy>,
+    /// Resource limits, none when unset
+    #[serde(default)]
+    pub limits: ResourceLimits,
+}
+
+impl ProcessSpec {
+    /// The dapp given on the command line, configured from the command line flags
+    pub fn dapp(command: Vec<String>, config: &Config) -> Self {
+        Self {
+            name: DAPP.to_string(),
+            command,
+            env: config.dapp_env.clone(),
+            clear_env: config.dapp_clear_env,
+            cwd: config.dapp_workdir.clone(),
+            depends_on: Vec::new(),
+            ready: config.dapp_readiness.clone(),
+            restart: None,
+            limits: config.dapp_limits.clone(),
+        }
+    }
+}
+
+/// Processes listed in a TOML or JSON file, as `[[processes]]` tables or a `processes` array
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ProcessManifest {
+    #[serde(default)]
+    pub processes: Vec<ProcessSpec>,
+}
+
+impl ProcessManifest {
+    /// Read a manifest, parsed as JSON when the file name ends in `.json` and as TOML otherwise
+    pub fn load(path: &Path) -> std::io::Result<Self> {
+        let content = std::fs::read_to_string(path)?;
+        let manifest: Self = if path.extension().is_some_and(|ext| ext == "json") {
+            serde_json::from_str(&content).map_err(|e| Error::new(ErrorKind::InvalidData, e))?
+        } else {
+            toml::from_str(&content).map_err(|e| Error::new(ErrorKind::InvalidData, e))?
+        };
+        Ok(manifest)
+    }
+
+    /// Processes in the order they have to be started, dependencies first and the manifest
+    /// order otherwise. One of them has to be the dapp, whose readiness salsa reports.
+    pub fn start_order(&self) -> Result<Vec<ProcessSpec>, String> {
+        if !self.processes.is_empty() && !self.processes.iter().any(|process| process.name == DAPP)
+        {
+            return Err(format!(
+                "the manifest has no '{}' process, give a command or name one of them '{}'",
+                DAPP, DAPP
+            ));
+        }
+        let mut names = HashSet::new();
+        for process in &self.processes {
+            if process.command.is_empty() {
+                return Err(format!("process '{}' has an empty command", process.name));
+            }
+            if !names.insert(process.name.as_str()) {
+                return Err(format!("process '{}' is listed twice", process.name));
+            }
+        }
+        for process in &self.processes {
+            if let Some(dependency) = process
+                .depends_on
+                .iter()
+                .find(|dependency| !names.contains(dependency.as_str()))
+            {
+                return Err(format!(
+                    "process '{}' depends on unknown process '{}'",
+                    process.name, dependency
+                ));
+            }
+        }
+        let mut started: HashSet<&str> = HashSet::new();
+        let mut order = Vec::new();
+        while order.len() < self.processes.len() {
+            let next = self.processes.iter().find(|process| {
+                !started.contains(process.name.as_str())
+                    && process
+                        .depends_on
+                        .iter()
+                        .all(|dependency| started.contains(dependency.as_str()))
+            });
+            match next {
+                Some(process) => {
+                    started.insert(&process.name);
+                    order.push(process.clone());
+                }
+                None => {
+                    let cycle: Vec<&str> = self
+                        .processes
+                        .iter()
+                        .map(|process| process.name.as_str())
+                        .filter(|name| !started.contains(name))
+                        .collect();
+                    return Err(format!(
+                        "processes {} depend on each other",
+                        cycle.join(", ")
+                    ));
+                }
+            }
+        }
+        Ok(order)
+    }
+
+    /// Add the dapp given on the command line, started once every listed process is ready
+    pub fn add_dapp(&mut self, mut dapp: ProcessSpec) -> Result<(), String> {
+        if self.processes.iter().any(|process| process.name == DAPP) {
+            return Err(format!(
+                "the manifest already has a '{}' process, no command can be given",
+                DAPP
+            ));
+        }
+        dapp.depends_on = self
+            .processes
+            .iter()
+            .map(|process| process.name.clone())
+            .collect();
+        self.processes.push(dapp);
+        Ok(())
+    }
+}
@@ -0,0 +1,207 @@
+//! Prometheus metrics of salsa, served by the `/metrics` endpoint.
+
+use std::sync::OnceLock;
+use std::time::Duration;
+
+use prometheus::{
+    HistogramOpts, HistogramVec, IntCounterVec, IntGauge, IntGaugeVec, Opts, Registry, TextEncoder,
+};
+
+use crate::dapp_process::ProcessState;
+use crate::rollup::cache::CacheStats;
+
+pub struct Metrics {
+    registry: Registry,
+    http_requests: IntCounterVec,
+    http_duration: HistogramVec,
+    gio_requests: IntCounterVec,
+    gio_duration: HistogramVec,
+    gio_payload_bytes: IntCounterVec,
+    ipfs_duration: HistogramVec,
+    gio_cache: IntGaugeVec,
+    process_restarts: IntGaugeVec,
+    process_running: IntGaugeVec,
+    processes: IntGauge,
+}
+
+fn register<M: prometheus::core::Collector + Clone + 'static>(registry: &Registry, metric: M) -> M {
+    registry
+        .register(Box::new(metric.clone()))
+        .expect("metric is registered once");
+    metric
+}
+
+impl Metrics {
+    fn new() -> Self {
+        let registry =
+            Registry::new_custom(Some("salsa".to_string()), None).expect("salsa is a valid prefix");
+        Self {
+            http_requests: register(
+                &registry,
+                IntCounterVec::new(
+                    Opts::new("http_requests_total", "HTTP requests by route and status"),
+                    &["method", "route", "status"],
+                )
+                .unwrap(),
+            ),
+            http_duration: register(
+                &registry,
+                HistogramVec::new(
+                    HistogramOpts::new(
+                        "http_request_duration_seconds",
+                        "Time spent answering HTTP requests",
+                    ),
+                    &["method", "route"],
+                )
+                .unwrap(),
+            ),
+            gio_requests: register(
+                &registry,
+                IntCounterVec::new(
+                    Opts::new(
+                        "gio_requests_total",
+                        "GIO requests by domain and response code, `error` when none came back",
+                    ),
+                    &["domain", "response_code"],
+                )
+                .unwrap(),
+            ),
+            gio_duration: register(
+                &registry,
+                HistogramVec::new(
+                    HistogramOpts::new(
+                        "gio_request_duration_seconds",
+                        "Time spent answering GIO requests, cache hits included",
+                    ),
+                    &["domain"],
+                )
+                .unwrap(),
+            ),
+            gio_payload_bytes: register(
+                &registry,
+                IntCounterVec::new(
+                    Opts::new(
+                        "gio_payload_bytes_total",
+                        "Bytes of GIO payloads sent and responses received",
+                    ),
+                    &["domain", "direction"],
+                )
+                .unwrap(),
+            ),
+            ipfs_duration: register(
+                &registry,
+                HistogramVec::new(
+                    HistogramOpts::new(
+                        "ipfs_request_duration_seconds",
+                        "Time spent in IPFS calls by operation",
+                    ),
+                    &["operation", "outcome"],
+                )
+                .unwrap(),
+            ),
+            gio_cache: register(
+                &registry,
+                IntGaugeVec::new(
+                    Opts::new("gio_cache", "GIO cache hits, misses, entries and bytes"),
+                    &["stat"],
+                )
+                .unwrap(),
+            ),
+            process_restarts: register(
+                &registry,
+                IntGaugeVec::new(
+                    Opts::new("process_restarts", "Restarts of each supervised process"),
+                    &["process"],
+                )
+                .unwrap(),
+            ),
+            process_running: register(
+                &registry,
+                IntGaugeVec::new(
+                    Opts::new(
+                        "process_running",
+                        "Whether each supervised process is running",
+                    ),
+                    &["process"],
+                )
+                .unwrap(),
+            ),
+            processes: register(
+                &registry,
+                IntGauge::new("processes", "Number of supervised processes").unwrap(),
+            ),
+            registry,
+        }
+    }
+
+    pub fn observe_http(&self, method: &str, route: &str, status: u16, elapsed: Duration) {
+        self.http_requests
+            .with_label_values(&[method, route, &status.to_string()])
+            .inc();
+        self.http_duration
+            .with_label_values(&[method, route])
+            .observe(elapsed.as_secs_f64());
+    }
+
+    /// Record a GIO exchange, `response_code` is `None` when it failed
+    pub fn observe_gio(
+        &self,
+        domain: &str,
+        response_code: Option<u16>,
+        payload_bytes: usize,
+        response_bytes: usize,
+        elapsed: Duration,
+    ) {
+        let code = response_code.map_or_else(|| "error".to_string(), |code| code.to_string());
+        self.gio_requests.with_label_values(&[domain, &code]).inc();
+        self.gio_duration
+            .with_label_values(&[domain])
+            .observe(elapsed.as_secs_f64());
+        self.gio_payload_bytes
+            .with_label_values(&[domain, "request"])
+            .inc_by(payload_bytes as u64);
+        self.gio_payload_bytes
+            .with_label_values(&[domain, "response"])
+            .inc_by(response_bytes as u64);
+    }
+
+    pub fn observe_ipfs(&self, operation: &str, success: bool, elapsed: Duration) {
+        let outcome = if success { "ok" } else { "error" };
+        self.ipfs_duration
+            .with_label_values(&[operation, outcome])
+            .observe(elapsed.as_secs_f64());
+    }
+
+    /// Render every metric in the Prometheus text format, sampling the cache and the
+    /// supervised processes first
+    pub fn render(&self, cache: Option<CacheStats>, processes: &[ProcessState]) -> String {
+        if let Some(stats) = cache {
+            for (stat, value) in [
+                ("hits", stats.hits),
+                ("misses", stats.misses),
+                ("entries", stats.entries as u64),
+                ("bytes", stats.bytes as u64),
+            ] {
+                self.gio_cache.with_label_values(&[stat]).set(value as i64);
+            }
+        }
+        self.processes.set(processes.len() as i64);
+        for process in processes {
+            self.process_restarts
+                .with_label_values(&[&process.name])
+                .set(process.restarts as i64);
+            self.process_running
+                .with_label_values(&[&process.name])
+                .set(process.running as i64);
+        }
+        TextEncoder::new()
+            .encode_to_string(&self.registry.gather())
+            .unwrap_or_default()
+    }
+}
+
+/// Metrics shared by every part of salsa
+pub fn metrics() -> &'static Metrics {
+    static METRICS: OnceLock<Metrics> = OnceLock::new();
+    METRICS.get_or_init(Metrics::new)
+}
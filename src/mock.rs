@@ -0,0 +1,253 @@
+// Copyright Cartesi and individual authors (see AUTHORS)
+// SPDX-License-Identifier: Apache-2.0
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+//! In-memory rollup http server answering every GIO domain salsa uses, so that
+//! salsa and dapps can be exercised without the Cartesi machine.
+
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use actix_web::{web, App, HttpResponse, HttpServer};
+use cid::multihash::Multihash;
+use cid::Cid;
+use sha2::Sha256;
+use sha3::{Digest, Sha3_256};
+
+use crate::rollup::{
+    Exception, GIORequest, GIOResponse, CURRENT_STATE_CID, EXTERNALIZE_STATE, HINT, IPFS_GET_BLOCK,
+    KECCAK256_NAMESPACE, METADATA, SET_STATE_CID,
+};
+
+/// Response code of a GIO request that was answered
+pub const RESPONSE_OK: u16 = 0;
+/// Response code of a GIO request for something the mock does not know about
+pub const RESPONSE_NOT_FOUND: u16 = 1;
+
+const SHA2_256: u64 = 0x12;
+const MAX_REQUEST_SIZE: usize = 256 * 1024 * 1024;
+
+#[derive(Debug, Default)]
+struct MockState {
+    state_cid: Vec<u8>,
+    metadata: HashMap<Vec<u8>, Vec<u8>>,
+    preimages: HashMap<Vec<u8>, Vec<u8>>,
+    blocks: HashMap<Vec<u8>, Vec<u8>>,
+    hints: Vec<Vec<u8>>,
+    requests: Vec<GIORequest>,
+    received: Vec<Instant>,
+    delay: Duration,
+    exceptions: Vec<Vec<u8>>,
+    scripted: HashMap<u16, VecDeque<GIOResponse>>,
+}
+
+/// Handle to the state of a mock rollup server, clones share the same state
+#[derive(Debug, Clone, Default)]
+pub struct MockRollup {
+    state: Arc<Mutex<MockState>>,
+}
+
+pub(crate) fn ok(response: impl AsRef<[u8]>) -> GIOResponse {
+    GIOResponse {
+        response_code: RESPONSE_OK,
+        response: format!("0x{}", hex::encode(response)),
+    }
+}
+
+pub(crate) fn not_found() -> GIOResponse {
+    GIOResponse {
+        response_code: RESPONSE_NOT_FOUND,
+        response: "0x".to_string(),
+    }
+}
+
+pub(crate) fn decode(payload: &str) -> Vec<u8> {
+    hex::decode(payload.strip_prefix("0x").unwrap_or(payload)).unwrap_or_default()
+}
+
+/// Key of a block in the store, the multihash so that any codec finds the same data
+pub(crate) fn block_key(cid: &[u8]) -> Option<Vec<u8>> {
+    Cid::try_from(cid).ok().map(|cid| cid.hash().to_bytes())
+}
+
+/// Key under which an externalized block is stored, its sha2-256 multihash
+pub(crate) fn externalized_block_key(data: &[u8]) -> Vec<u8> {
+    let digest = Sha256::digest(data);
+    Multihash::<64>::wrap(SHA2_256, &digest).unwrap().to_bytes()
+}
+
+impl MockRollup {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// CID returned for `CURRENT_STATE_CID`, updated by `SET_STATE_CID`
+    pub fn set_state_cid(&self, cid: impl AsRef<[u8]>) {
+        self.state.lock().unwrap().state_cid = cid.as_ref().to_vec();
+    }
+
+    pub fn state_cid(&self) -> Vec<u8> {
+        self.state.lock().unwrap().state_cid.clone()
+    }
+
+    /// Value returned for `METADATA` lookups of `name`, keyed by its SHA3-256 like salsa does
+    pub fn set_metadata(&self, name: &str, value: impl AsRef<[u8]>) {
+        let key = Sha3_256::digest(name.as_bytes()).to_vec();
+        self.state
+            .lock()
+            .unwrap()
+            .metadata
+            .insert(key, value.as_ref().to_vec());
+    }
+
+    /// Data returned for `KECCAK256_NAMESPACE` requests whose payload is exactly `key`
+    pub fn set_preimage(&self, key: impl AsRef<[u8]>, data: impl AsRef<[u8]>) {
+        self.state
+            .lock()
+            .unwrap()
+            .preimages
+            .insert(key.as_ref().to_vec(), data.as_ref().to_vec());
+    }
+
+    /// Block returned for `IPFS_GET_BLOCK` requests on `cid`
+    pub fn add_block(&self, cid: &Cid, data: impl AsRef<[u8]>) {
+        self.state
+            .lock()
+            .unwrap()
+            .blocks
+            .insert(cid.hash().to_bytes(), data.as_ref().to_vec());
+    }
+
+    /// Block previously added or externalized, looked up by its multihash
+    pub fn block(&self, cid: &Cid) -> Option<Vec<u8>> {
+        let state = self.state.lock().unwrap();
+        state.blocks.get(&cid.hash().to_bytes()).cloned()
+    }
+
+    /// Answer the next request on `domain` with `response` instead of the emulated one
+    pub fn script(&self, domain: u16, response: GIOResponse) {
+        self.state
+            .lock()
+            .unwrap()
+            .scripted
+            .entry(domain)
+            .or_default()
+            .push_back(response);
+    }
+
+    /// Hold every answer, GIO or exception, for `delay` after recording the request
+    pub fn set_delay(&self, delay: Duration) {
+        self.state.lock().unwrap().delay = delay;
+    }
+
+    /// When each of the GIO requests received so far arrived, in order
+    pub fn request_times(&self) -> Vec<Instant> {
+        self.state.lock().unwrap().received.clone()
+    }
+
+    /// Every GIO request received so far, in order
+    pub fn requests(&self) -> Vec<GIORequest> {
+        self.state.lock().unwrap().requests.clone()
+    }
+
+    /// Payloads of the hints received so far
+    pub fn hints(&self) -> Vec<Vec<u8>> {
+        self.state.lock().unwrap().hints.clone()
+    }
+
+    /// Decoded payloads of the exceptions thrown so far
+    pub fn exceptions(&self) -> Vec<Vec<u8>> {
+        self.state.lock().unwrap().exceptions.clone()
+    }
+
+    /// Emulate the rollup server answer to a GIO request
+    pub fn handle_gio(&self, request: &GIORequest) -> GIOResponse {
+        let mut state = self.state.lock().unwrap();
+        state.requests.push(request.clone());
+        state.received.push(Instant::now());
+        if let Some(response) = state
+            .scripted
+            .get_mut(&request.domain)
+            .and_then(|responses| responses.pop_front())
+        {
+            return response;
+        }
+        let payload = decode(&request.payload);
+        match request.domain {
+            CURRENT_STATE_CID => ok(&state.state_cid),
+            SET_STATE_CID => {
+                state.state_cid = payload;
+                ok([])
+            }
+            METADATA => state.metadata.get(&payload).map_or_else(not_found, ok),
+            KECCAK256_NAMESPACE => state.preimages.get(&payload).map_or_else(not_found, ok),
+            EXTERNALIZE_STATE => {
+                state
+                    .blocks
+                    .insert(externalized_block_key(&payload), payload);
+                ok([])
+            }
+            IPFS_GET_BLOCK => block_key(&payload)
+                .and_then(|key| state.blocks.get(&key))
+                .map_or_else(not_found, ok),
+            HINT => {
+                state.hints.push(payload);
+                ok([])
+            }
+            _ => not_found(),
+        }
+    }
+
+    async fn hold(&self) {
+        let delay = self.state.lock().unwrap().delay;
+        if !delay.is_zero() {
+            tokio::time::sleep(delay).await;
+        }
+    }
+
+    /// Create a http server serving `/gio` and `/exception` from this mock
+    pub fn serve(&self, address: &str, port: u16) -> std::io::Result<actix_server::Server> {
+        let mock = web::Data::new(self.clone());
+        let server = HttpServer::new(move || {
+            App::new()
+                .app_data(mock.clone())
+                .app_data(web::JsonConfig::default().limit(MAX_REQUEST_SIZE))
+                .route("/gio", web::post().to(gio))
+                .route("/exception", web::post().to(exception))
+        })
+        .bind((address, port))?
+        .run();
+        Ok(server)
+    }
+}
+
+async fn gio(mock: web::Data<MockRollup>, request: web::Json<GIORequest>) -> HttpResponse {
+    log::debug!("mock rollup received gio request {:?}", request);
+    let response = mock.handle_gio(&request);
+    mock.hold().await;
+    HttpResponse::Ok().json(response)
+}
+
+async fn exception(mock: web::Data<MockRollup>, exception: web::Json<Exception>) -> HttpResponse {
+    log::debug!("mock rollup received exception {:?}", exception);
+    mock.state
+        .lock()
+        .unwrap()
+        .exceptions
+        .push(decode(&exception.payload));
+    mock.hold().await;
+    HttpResponse::Ok().finish()
+}
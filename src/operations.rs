@@ -0,0 +1,191 @@
+//! Operations of the http service on the state, the rollup server and IPFS, shared by the
+//! REST routes and the JSON-RPC interface.
+
+use std::fmt;
+
+use actix_web::http::StatusCode;
+use actix_web::web::Bytes;
+use cid::Cid;
+use sha3::{Digest, Sha3_256};
+
+use crate::app::{self, AppError, LAMBADA_APP};
+use crate::config::Config;
+use crate::ipfs::{Ipfs, IpfsError};
+use crate::rollup::client::{RollupClient, RollupError};
+use crate::rollup::{
+    CURRENT_STATE_CID, EXTERNALIZE_STATE, HINT, IPFS_GET_BLOCK, KECCAK256_NAMESPACE, METADATA,
+    SET_STATE_CID,
+};
+
+/// Directory of MFS holding the state entries
+const STATE_PATH: &str = "/state";
+
+/// Failure of one of the backends an operation depends on
+#[derive(Debug)]
+pub enum ServiceError {
+    Rollup(RollupError),
+    Ipfs(IpfsError),
+    /// The backend answered, but with data that could not be interpreted
+    InvalidData(String),
+}
+
+impl ServiceError {
+    /// Status of the REST answer, timeouts are a 504
+    pub fn status(&self) -> StatusCode {
+        match self {
+            ServiceError::Rollup(RollupError::PayloadTooLarge { .. }) => {
+                StatusCode::PAYLOAD_TOO_LARGE
+            }
+            ServiceError::Rollup(e) if e.is_timeout() => StatusCode::GATEWAY_TIMEOUT,
+            ServiceError::Ipfs(e) if e.is_timeout() => StatusCode::GATEWAY_TIMEOUT,
+            ServiceError::Ipfs(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            _ => StatusCode::BAD_REQUEST,
+        }
+    }
+}
+
+impl fmt::Display for ServiceError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ServiceError::Rollup(e) => write!(f, "{}", e),
+            ServiceError::Ipfs(e) => write!(f, "{}", e),
+            ServiceError::InvalidData(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl From<AppError> for ServiceError {
+    fn from(e: AppError) -> Self {
+        match e {
+            AppError::Ipfs(e) => ServiceError::Ipfs(e),
+            AppError::Invalid(e) => ServiceError::InvalidData(e),
+        }
+    }
+}
+
+impl From<RollupError> for ServiceError {
+    fn from(e: RollupError) -> Self {
+        ServiceError::Rollup(e)
+    }
+}
+
+impl From<IpfsError> for ServiceError {
+    fn from(e: IpfsError) -> Self {
+        ServiceError::Ipfs(e)
+    }
+}
+
+/// Send a GIO request and decode its hex response
+async fn gio_bytes(
+    rollup: &RollupClient,
+    domain: u16,
+    payload: impl AsRef<[u8]>,
+) -> Result<Vec<u8>, ServiceError> {
+    rollup
+        .call(domain, payload)
+        .await?
+        .response_bytes()
+        .map_err(|e| ServiceError::InvalidData(e.to_string()))
+}
+
+/// Send a GIO request whose response is a binary CID
+async fn gio_cid(
+    rollup: &RollupClient,
+    domain: u16,
+    payload: impl AsRef<[u8]>,
+) -> Result<Cid, ServiceError> {
+    let response = gio_bytes(rollup, domain, payload).await?;
+    Cid::try_from(response).map_err(|e| ServiceError::InvalidData(e.to_string()))
+}
+
+/// Content of the state entry `key`
+pub async fn read_state(ipfs: &Ipfs, key: &str) -> Result<Bytes, ServiceError> {
+    Ok(ipfs.files_read(&format!("{}/{}", STATE_PATH, key)).await?)
+}
+
+/// Write `value` to the state entry `key`
+pub async fn write_state(ipfs: &Ipfs, key: &str, value: Bytes) -> Result<(), ServiceError> {
+    let _ = ipfs.files_mkdir(STATE_PATH).await;
+    ipfs.files_write(&format!("{}/{}", STATE_PATH, key), value)
+        .await?;
+    Ok(())
+}
+
+/// Remove the state entry `key`
+pub async fn delete_state(ipfs: &Ipfs, key: &str) -> Result<(), ServiceError> {
+    ipfs.files_rm(&format!("{}/{}", STATE_PATH, key)).await?;
+    Ok(())
+}
+
+/// Copy the current state of the rollup server to `/state`, the previous one is kept in
+/// `/state/previous`
+pub async fn open_state(rollup: &RollupClient, ipfs: &Ipfs) -> Result<(), ServiceError> {
+    let cid = gio_cid(rollup, CURRENT_STATE_CID, []).await?;
+    let cid_path = format!("/ipfs/{}", cid);
+
+    // Updates new state using cid received from rollup_http_server gio request
+    ipfs.files_cp(&cid_path, "/state-new").await?;
+    ipfs.files_rm("/state-new/previous").await?;
+    ipfs.files_cp(&cid_path, "/state-new/previous").await?;
+    ipfs.files_rm(STATE_PATH).await?;
+    ipfs.files_mv("/state-new", STATE_PATH).await?;
+    Ok(())
+}
+
+/// Set the CID of `/state` as the new state of the rollup server
+pub async fn commit_state(rollup: &RollupClient, ipfs: &Ipfs) -> Result<(), ServiceError> {
+    let cid = ipfs.files_stat_hash(STATE_PATH).await?;
+    let cid = Cid::try_from(cid).map_err(|e| ServiceError::InvalidData(e.to_string()))?;
+
+    // rollup_http_server gio request with cid received from /state
+    rollup.call(SET_STATE_CID, cid.to_bytes()).await?;
+    Ok(())
+}
+
+/// Value of the metadata entry named `text`, looked up by its sha3-256
+pub async fn metadata(rollup: &RollupClient, text: &str) -> Result<Vec<u8>, ServiceError> {
+    gio_bytes(rollup, METADATA, Sha3_256::digest(text.as_bytes())).await
+}
+
+/// Preimage of `data_id`, only the keccak256 namespace is supported
+pub async fn data(
+    rollup: &RollupClient,
+    namespace: &str,
+    data_id: &str,
+) -> Result<Vec<u8>, ServiceError> {
+    if namespace != "keccak256" {
+        return Err(ServiceError::InvalidData(
+            "namespace should be keccak256".to_string(),
+        ));
+    }
+    gio_bytes(rollup, KECCAK256_NAMESPACE, data_id.as_bytes()).await
+}
+
+/// Content of the IPFS block `cid`, fetched through the rollup server
+pub async fn ipfs_get(rollup: &RollupClient, cid: &str) -> Result<Vec<u8>, ServiceError> {
+    let cid = Cid::try_from(cid).map_err(|e| ServiceError::InvalidData(e.to_string()))?;
+    gio_bytes(rollup, IPFS_GET_BLOCK, cid.to_bytes()).await
+}
+
+/// Externalize the IPFS block `content` through the rollup server
+pub async fn ipfs_put(rollup: &RollupClient, content: Bytes) -> Result<Vec<u8>, ServiceError> {
+    gio_bytes(rollup, EXTERNALIZE_STATE, content).await
+}
+
+/// Pass the hint `what` on to the rollup server
+pub async fn hint(rollup: &RollupClient, what: &str) -> Result<Vec<u8>, ServiceError> {
+    gio_bytes(rollup, HINT, what).await
+}
+
+/// Copy the app named by the `lambada-app` metadata to `/app` and return its CID
+pub async fn get_app(
+    config: &Config,
+    rollup: &RollupClient,
+    ipfs: &Ipfs,
+) -> Result<Cid, ServiceError> {
+    let cid = gio_cid(rollup, METADATA, Sha3_256::digest(LAMBADA_APP.as_bytes())).await?;
+
+    // Updates new app using cid received from rollup_http_server gio request
+    app::install(ipfs, &cid, config.app_cid.as_deref()).await?;
+    Ok(cid)
+}
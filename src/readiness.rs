@@ -0,0 +1,56 @@
+//! Probes telling whether a process started by salsa is ready to serve.
+
+use std::time::Duration;
+
+use http_body_util::Empty;
+use hyper::body::Bytes;
+
+use crate::config::ReadinessProbe;
+use crate::utils;
+
+/// Delay between two probe attempts
+const PROBE_INTERVAL: Duration = Duration::from_millis(100);
+/// Maximum time a single probe attempt may take
+const PROBE_TIMEOUT: Duration = Duration::from_secs(1);
+
+async fn probe_once(probe: &ReadinessProbe) -> bool {
+    match probe {
+        ReadinessProbe::Http(url) => {
+            let client = utils::create_client();
+            let req = match hyper::Request::builder()
+                .method(hyper::Method::GET)
+                .uri(url)
+                .body(Empty::<Bytes>::new())
+            {
+                Ok(req) => req,
+                Err(e) => {
+                    log::error!("readiness url '{}' is not valid: {}", url, e);
+                    return false;
+                }
+            };
+            client
+                .request(req)
+                .await
+                .is_ok_and(|res| res.status().is_success())
+        }
+        ReadinessProbe::Tcp(address) => tokio::net::TcpStream::connect(address).await.is_ok(),
+        ReadinessProbe::File(path) => tokio::fs::try_exists(path).await.unwrap_or(false),
+    }
+}
+
+/// Check the probe once, bounded by the probe timeout
+pub async fn is_ready(probe: &ReadinessProbe) -> bool {
+    tokio::time::timeout(PROBE_TIMEOUT, probe_once(probe))
+        .await
+        .unwrap_or(false)
+}
+
+/// Poll the probe until it succeeds, returns false if it did not within `timeout`
+pub async fn wait_ready(probe: &ReadinessProbe, timeout: Duration) -> bool {
+    let poll = async {
+        while !is_ready(probe).await {
+            tokio::time::sleep(PROBE_INTERVAL).await;
+        }
+    };
+    tokio::time::timeout(timeout, poll).await.is_ok()
+}
@@ -0,0 +1,116 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+
+use lru::LruCache;
+
+use super::{GIORequest, GIOResponse, IPFS_GET_BLOCK, KECCAK256_NAMESPACE};
+
+/// Whether the response on this domain is a pure function of the payload
+pub fn is_immutable(domain: u16) -> bool {
+    matches!(domain, IPFS_GET_BLOCK | KECCAK256_NAMESPACE)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CacheStats {
+    pub hits: u64,
+    pub misses: u64,
+    pub entries: usize,
+    pub bytes: usize,
+}
+
+struct Entries {
+    lru: LruCache<(u16, String), GIOResponse>,
+    bytes: usize,
+}
+
+/// Bounded LRU cache of GIO responses on immutable domains, keyed by domain and payload
+pub struct GioCache {
+    entries: Mutex<Entries>,
+    max_entries: usize,
+    max_bytes: usize,
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+impl std::fmt::Debug for GioCache {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("GioCache")
+            .field("max_entries", &self.max_entries)
+            .field("max_bytes", &self.max_bytes)
+            .field("stats", &self.stats())
+            .finish()
+    }
+}
+
+fn entry_size(request: &GIORequest, response: &GIOResponse) -> usize {
+    request.payload.len() + response.response.len()
+}
+
+impl GioCache {
+    pub fn new(max_entries: usize, max_bytes: usize) -> Self {
+        Self {
+            entries: Mutex::new(Entries {
+                lru: LruCache::unbounded(),
+                bytes: 0,
+            }),
+            max_entries,
+            max_bytes,
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+        }
+    }
+
+    /// Look up a previous response, only requests on immutable domains are ever found
+    pub fn get(&self, request: &GIORequest) -> Option<GIOResponse> {
+        if !is_immutable(request.domain) {
+            return None;
+        }
+        let mut entries = self.entries.lock().unwrap();
+        let key = (request.domain, request.payload.clone());
+        match entries.lru.get(&key) {
+            Some(response) => {
+                self.hits.fetch_add(1, Ordering::Relaxed);
+                Some(response.clone())
+            }
+            None => {
+                self.misses.fetch_add(1, Ordering::Relaxed);
+                None
+            }
+        }
+    }
+
+    /// Remember a successful response, evicting the least recently used entries over the limits
+    pub fn insert(&self, request: &GIORequest, response: &GIOResponse) {
+        if !is_immutable(request.domain) || response.response_code != 0 {
+            return;
+        }
+        let size = entry_size(request, response);
+        if size > self.max_bytes || self.max_entries == 0 {
+            return;
+        }
+        let mut entries = self.entries.lock().unwrap();
+        let key = (request.domain, request.payload.clone());
+        if let Some(previous) = entries.lru.put(key, response.clone()) {
+            entries.bytes -= request.payload.len() + previous.response.len();
+        }
+        entries.bytes += size;
+        while entries.lru.len() > self.max_entries || entries.bytes > self.max_bytes {
+            match entries.lru.pop_lru() {
+                Some(((_, payload), evicted)) => {
+                    entries.bytes -= payload.len() + evicted.response.len();
+                }
+                None => break,
+            }
+        }
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        let entries = self.entries.lock().unwrap();
+        CacheStats {
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+            entries: entries.lru.len(),
+            bytes: entries.bytes,
+        }
+    }
+}
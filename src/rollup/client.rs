@@ -0,0 +1,308 @@
+use std::fmt;
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime};
+
+use http_body_util::Full;
+use hyper::body::Bytes;
+use hyper_util::client::legacy::{connect::HttpConnector, Client};
+use tracing::Instrument;
+
+use super::cache::{CacheStats, GioCache};
+use super::trace::{TraceEntry, TraceRecorder, TraceReplay};
+use super::{domain_name, is_idempotent, Exception, GIORequest, GIOResponse};
+use crate::config::Config;
+use crate::dev::LocalBackend;
+use crate::metrics::metrics;
+use crate::utils;
+
+#[derive(Debug)]
+pub enum RollupError {
+    /// The rollup server did not answer within the configured timeout
+    Timeout(Duration),
+    /// The request could not be delivered to the rollup server
+    Request(String),
+    /// The rollup server answered with something that is not a valid response
+    InvalidResponse(String),
+    /// The request does not match the next exchange of the trace being replayed
+    Divergence(String),
+    /// The payload does not fit in a GIO request, nothing was sent
+    PayloadTooLarge { size: usize, limit: usize },
+}
+
+impl RollupError {
+    pub fn is_timeout(&self) -> bool {
+        matches!(self, RollupError::Timeout(_))
+    }
+}
+
+impl fmt::Display for RollupError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RollupError::Timeout(timeout) => {
+                write!(f, "rollup server did not answer within {:?}", timeout)
+            }
+            RollupError::Request(e) => write!(f, "{}", e),
+            RollupError::InvalidResponse(e) => write!(f, "invalid rollup server response: {}", e),
+            RollupError::Divergence(e) => write!(f, "{}", e),
+            RollupError::PayloadTooLarge { size, limit } => write!(
+                f,
+                "payload of {} bytes exceeds the {} bytes a GIO request can carry",
+                size, limit
+            ),
+        }
+    }
+}
+
+impl std::error::Error for RollupError {}
+
+/// Client for the rollup http server endpoints used by salsa
+#[derive(Debug, Clone)]
+pub struct RollupClient {
+    client: Client<HttpConnector, Full<Bytes>>,
+    url: String,
+    timeout: Duration,
+    retries: u32,
+    backoff: Duration,
+    max_payload: usize,
+    cache: Option<Arc<GioCache>>,
+    recorder: Option<Arc<TraceRecorder>>,
+    replay: Option<Arc<TraceReplay>>,
+    local: Option<Arc<LocalBackend>>,
+}
+
+impl RollupClient {
+    /// Create a client for the configured rollup server, opening the GIO trace files if any
+    pub fn new(config: &Config) -> std::io::Result<Self> {
+        let recorder = match &config.gio_record {
+            Some(path) => Some(Arc::new(TraceRecorder::create(path)?)),
+            None => None,
+        };
+        let replay = match &config.gio_replay {
+            Some(path) => Some(Arc::new(TraceReplay::open(path)?)),
+            None => None,
+        };
+        let local = match &config.dev {
+            Some(dev) => Some(Arc::new(LocalBackend::open(dev)?)),
+            None => None,
+        };
+        Ok(Self {
+            client: utils::create_client(),
+            url: config.rollup_url.trim_end_matches('/').to_string(),
+            timeout: config.gio_timeout,
+            retries: config.gio_retries,
+            backoff: config.gio_backoff,
+            max_payload: config.gio_max_payload,
+            cache: config.gio_cache_enabled.then(|| {
+                Arc::new(GioCache::new(
+                    config.gio_cache_entries,
+                    config.gio_cache_bytes,
+                ))
+            }),
+            recorder,
+            replay,
+            local,
+        })
+    }
+
+    /// Hit and miss counters of the response cache, if it is enabled
+    pub fn cache_stats(&self) -> Option<CacheStats> {
+        self.cache.as_ref().map(|cache| cache.stats())
+    }
+
+    /// Exchanges of the replayed trace that were not asked for yet, if one is replayed
+    pub fn replay_remaining(&self) -> Option<usize> {
+        self.replay.as_ref().map(|replay| replay.remaining())
+    }
+
+    /// Send a GIO request, or answer it from the trace when replaying one
+    pub async fn gio(&self, request: &GIORequest) -> Result<GIOResponse, RollupError> {
+        let started = Instant::now();
+        let hex_len = |hex: &str| hex.trim_start_matches("0x").len() / 2;
+        let domain = domain_name(request.domain);
+        let payload_bytes = hex_len(&request.payload);
+        let span = tracing::info_span!(
+            "gio",
+            otel.kind = "client",
+            domain = %domain,
+            payload_bytes,
+            response_code = tracing::field::Empty,
+            response_bytes = tracing::field::Empty,
+            error = tracing::field::Empty,
+        );
+        let result = self
+            .gio_uninstrumented(request)
+            .instrument(span.clone())
+            .await;
+        let response_bytes = result
+            .as_ref()
+            .map_or(0, |response| hex_len(&response.response));
+        match &result {
+            Ok(response) => {
+                span.record("response_code", response.response_code);
+                span.record("response_bytes", response_bytes);
+            }
+            Err(e) => {
+                span.record("error", tracing::field::display(e));
+            }
+        }
+        metrics().observe_gio(
+            &domain,
+            result.as_ref().ok().map(|response| response.response_code),
+            payload_bytes,
+            response_bytes,
+            started.elapsed(),
+        );
+        result
+    }
+
+    async fn gio_uninstrumented(&self, request: &GIORequest) -> Result<GIOResponse, RollupError> {
+        let size = request.payload.trim_start_matches("0x").len() / 2;
+        if size > self.max_payload {
+            return Err(RollupError::PayloadTooLarge {
+                size,
+                limit: self.max_payload,
+            });
+        }
+        if let Some(replay) = &self.replay {
+            let entry = replay.next(request).map_err(RollupError::Divergence)?;
+            return Ok(entry.response);
+        }
+        let started = SystemTime::now();
+        let response = match &self.local {
+            Some(local) => {
+                // The local backend reads and writes the disk, off the async workers
+                let local = local.clone();
+                let request = request.clone();
+                tokio::task::spawn_blocking(move || local.handle_gio(&request))
+                    .await
+                    .map_err(|e| RollupError::Request(e.to_string()))?
+                    .map_err(|e| {
+                        RollupError::InvalidResponse(format!("local gio backend failed: {}", e))
+                    })?
+            }
+            None => self.gio_cached(request).await?,
+        };
+        if let Some(recorder) = &self.recorder {
+            if let Err(e) = recorder.record(&TraceEntry::new(request, &response, started)) {
+                log::error!("unable to record gio exchange, error details: '{}'", e);
+            }
+        }
+        Ok(response)
+    }
+
+    /// Send a GIO request, answering from the cache when the response is already known
+    async fn gio_cached(&self, request: &GIORequest) -> Result<GIOResponse, RollupError> {
+        let Some(cache) = &self.cache else {
+            return self.gio_with_retries(request).await;
+        };
+        if let Some(response) = cache.get(request) {
+            return Ok(response);
+        }
+        let response = self.gio_with_retries(request).await?;
+        cache.insert(request, &response);
+        Ok(response)
+    }
+
+    /// Send a GIO request, retrying with exponential backoff on idempotent domains
+    async fn gio_with_retries(&self, request: &GIORequest) -> Result<GIOResponse, RollupError> {
+        let attempts = if is_idempotent(request.domain) {
+            self.retries + 1
+        } else {
+            1
+        };
+        let mut attempt = 0;
+        loop {
+            attempt += 1;
+            match self.try_gio(request).await {
+                Err(e @ (RollupError::Timeout(_) | RollupError::Request(_)))
+                    if attempt < attempts =>
+                {
+                    let delay = self.backoff.saturating_mul(1 << (attempt - 1).min(16));
+                    log::warn!(
+                        "gio request on domain {:#x} failed ({}), retrying in {:?}",
+                        request.domain,
+                        e,
+                        delay
+                    );
+                    tokio::time::sleep(delay).await;
+                }
+                result => return result,
+            }
+        }
+    }
+
+    /// Convenience wrapper around [`RollupClient::gio`] for raw payloads
+    pub async fn call(
+        &self,
+        domain: u16,
+        payload: impl AsRef<[u8]>,
+    ) -> Result<GIOResponse, RollupError> {
+        self.gio(&GIORequest::new(domain, payload)).await
+    }
+
+    /// Notify the rollup server that the dapp hit an unrecoverable condition
+    pub async fn throw_exception(&self, exception: &Exception) -> Result<(), RollupError> {
+        if self.replay.is_some() {
+            log::warn!("replaying gio trace, exception not sent: {:?}", exception);
+            return Ok(());
+        }
+        if self.local.is_some() {
+            let payload = exception.payload.trim_start_matches("0x");
+            let message = hex::decode(payload).unwrap_or_default();
+            log::error!("dapp exception: {}", String::from_utf8_lossy(&message));
+            return Ok(());
+        }
+        self.post("/exception", serde_json::to_string(exception).unwrap())
+            .await
+            .map(|_| ())
+    }
+
+    /// Check that the rollup server accepts requests, any answer counts. Always succeeds when
+    /// GIO requests are answered locally.
+    pub async fn probe(&self, timeout: Duration) -> Result<(), RollupError> {
+        if self.replay.is_some() || self.local.is_some() {
+            return Ok(());
+        }
+        let req = hyper::Request::builder()
+            .method(hyper::Method::GET)
+            .uri(&self.url)
+            .body(Full::new(Bytes::new()))
+            .map_err(|e| RollupError::Request(e.to_string()))?;
+        match tokio::time::timeout(timeout, self.client.request(req)).await {
+            Ok(result) => result
+                .map(|_| ())
+                .map_err(|e| RollupError::Request(e.to_string())),
+            Err(_) => Err(RollupError::Timeout(timeout)),
+        }
+    }
+
+    async fn try_gio(&self, request: &GIORequest) -> Result<GIOResponse, RollupError> {
+        let body = self
+            .post("/gio", serde_json::to_string(request).unwrap())
+            .await?;
+        serde_json::from_slice::<GIOResponse>(&body)
+            .map_err(|e| RollupError::InvalidResponse(e.to_string()))
+    }
+
+    async fn post(&self, path: &str, body: String) -> Result<Bytes, RollupError> {
+        let req = hyper::Request::builder()
+            .method(hyper::Method::POST)
+            .header(hyper::header::CONTENT_TYPE, "application/json")
+            .uri(format!("{}{}", self.url, path))
+            .body(utils::body_bytes(body))
+            .map_err(|e| RollupError::Request(e.to_string()))?;
+        let exchange = async {
+            let response = self
+                .client
+                .request(req)
+                .await
+                .map_err(|e| RollupError::Request(e.to_string()))?;
+            utils::response_to_bytes(response)
+                .await
+                .map_err(|e| RollupError::Request(e.to_string()))
+        };
+        tokio::time::timeout(self.timeout, exchange)
+            .await
+            .map_err(|_| RollupError::Timeout(self.timeout))?
+    }
+}
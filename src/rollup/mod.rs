@@ -2,9 +2,43 @@
 #![allow(non_camel_case_types)]
 #![allow(non_snake_case)]
 
+pub mod cache;
+pub mod client;
+pub mod trace;
+
+use std::collections::BTreeMap;
+
 use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
 use validator::Validate;
 
+pub const CURRENT_STATE_CID: u16 = 0x20;
+pub const SET_STATE_CID: u16 = 0x21;
+pub const METADATA: u16 = 0x22;
+pub const KECCAK256_NAMESPACE: u16 = 0x23;
+pub const EXTERNALIZE_STATE: u16 = 0x24;
+pub const IPFS_GET_BLOCK: u16 = 0x25;
+pub const HINT: u16 = 0x26;
+
+/// Name of a domain as used in metric labels, its number for unknown domains
+pub fn domain_name(domain: u16) -> String {
+    match domain {
+        CURRENT_STATE_CID => "current_state_cid".to_string(),
+        SET_STATE_CID => "set_state_cid".to_string(),
+        METADATA => "metadata".to_string(),
+        KECCAK256_NAMESPACE => "keccak256_namespace".to_string(),
+        EXTERNALIZE_STATE => "externalize_state".to_string(),
+        IPFS_GET_BLOCK => "ipfs_get_block".to_string(),
+        HINT => "hint".to_string(),
+        _ => format!("{:#x}", domain),
+    }
+}
+
+/// Whether repeating a request on this domain is guaranteed to have no side effects
+pub fn is_idempotent(domain: u16) -> bool {
+    matches!(domain, METADATA | IPFS_GET_BLOCK | KECCAK256_NAMESPACE)
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, Validate)]
 pub struct GIORequest {
     #[validate(range(min = 0x10))] // avoid overlapping with our HTIF_YIELD_MANUAL_REASON_*
@@ -12,13 +46,86 @@ pub struct GIORequest {
     pub payload: String,
 }
 
+impl GIORequest {
+    pub fn new(domain: u16, payload: impl AsRef<[u8]>) -> Self {
+        Self {
+            domain,
+            payload: format!("0x{}", hex::encode(payload)),
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct GIOResponse {
     pub response_code: u16,
     pub response: String,
 }
 
+impl GIOResponse {
+    /// Decode the hex encoded response, with or without the `0x` prefix
+    pub fn response_bytes(&self) -> Result<Vec<u8>, hex::FromHexError> {
+        let response = self.response.strip_prefix("0x").unwrap_or(&self.response);
+        hex::decode(response)
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Exception {
     pub payload: String,
-}
\ No newline at end of file
+}
+
+/// Structured content of an exception payload, serialized as JSON with a fixed field order
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct ExceptionReport {
+    /// What raised the exception, one of the `dapp_process` kinds or a kind chosen by the dapp
+    pub kind: String,
+    pub message: String,
+    /// Command line of the dapp
+    #[serde(default)]
+    pub command: Vec<String>,
+    #[serde(default)]
+    pub exit_code: Option<i32>,
+    /// Signal that terminated the dapp
+    #[serde(default)]
+    pub signal: Option<i32>,
+    /// Milliseconds since the unix epoch when the dapp was started
+    #[serde(default)]
+    pub started_at_ms: Option<u64>,
+    /// Milliseconds since the unix epoch when the dapp exited
+    #[serde(default)]
+    pub exited_at_ms: Option<u64>,
+    #[serde(default)]
+    pub restarts: u32,
+    /// Last lines the dapp wrote to stderr, oldest first
+    #[serde(default)]
+    pub stderr_tail: Vec<String>,
+    /// Free-form data attached by the dapp, kept sorted by key
+    #[serde(default)]
+    pub details: BTreeMap<String, serde_json::Value>,
+}
+
+impl ExceptionReport {
+    pub fn new(kind: impl Into<String>, message: impl Into<String>) -> Self {
+        Self {
+            kind: kind.into(),
+            message: message.into(),
+            ..Default::default()
+        }
+    }
+
+    pub fn to_exception(&self) -> Exception {
+        let json = serde_json::to_vec(self).expect("exception report serialization");
+        Exception {
+            payload: format!("0x{}", hex::encode(json)),
+        }
+    }
+
+    /// Decode the report from an exception payload, if it holds one
+    pub fn from_exception(exception: &Exception) -> Option<Self> {
+        let payload = exception
+            .payload
+            .strip_prefix("0x")
+            .unwrap_or(&exception.payload);
+        serde_json::from_slice(&hex::decode(payload).ok()?).ok()
+    }
+}
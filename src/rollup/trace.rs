@@ -0,0 +1,103 @@
+use std::collections::VecDeque;
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+
+use super::{GIORequest, GIOResponse};
+
+/// One GIO exchange as stored in a JSONL trace file
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TraceEntry {
+    /// Milliseconds since the unix epoch when the request was issued
+    pub timestamp_ms: u64,
+    /// How long the rollup server took to answer
+    pub elapsed_ms: u64,
+    pub request: GIORequest,
+    pub response: GIOResponse,
+}
+
+impl TraceEntry {
+    pub fn new(request: &GIORequest, response: &GIOResponse, started: SystemTime) -> Self {
+        Self {
+            timestamp_ms: started
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_millis() as u64,
+            elapsed_ms: started.elapsed().unwrap_or_default().as_millis() as u64,
+            request: request.clone(),
+            response: response.clone(),
+        }
+    }
+}
+
+/// Appends every GIO exchange to a trace file
+#[derive(Debug)]
+pub struct TraceRecorder {
+    file: Mutex<File>,
+}
+
+impl TraceRecorder {
+    pub fn create(path: &Path) -> std::io::Result<Self> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        Ok(Self {
+            file: Mutex::new(file),
+        })
+    }
+
+    pub fn record(&self, entry: &TraceEntry) -> std::io::Result<()> {
+        let mut line = serde_json::to_vec(entry)?;
+        line.push(b'\n');
+        let mut file = self.file.lock().unwrap();
+        file.write_all(&line)?;
+        file.flush()
+    }
+}
+
+/// Answers GIO requests from a previously recorded trace, in order
+#[derive(Debug)]
+pub struct TraceReplay {
+    entries: Mutex<VecDeque<TraceEntry>>,
+}
+
+impl TraceReplay {
+    pub fn open(path: &Path) -> std::io::Result<Self> {
+        let mut entries = VecDeque::new();
+        for line in BufReader::new(File::open(path)?).lines() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            entries.push_back(serde_json::from_str(&line)?);
+        }
+        Ok(Self {
+            entries: Mutex::new(entries),
+        })
+    }
+
+    /// Take the next recorded exchange, which must be for exactly the same request
+    pub fn next(&self, request: &GIORequest) -> Result<TraceEntry, String> {
+        let mut entries = self.entries.lock().unwrap();
+        let Some(entry) = entries.front() else {
+            return Err(format!(
+                "trace exhausted, no recorded response for domain {:#x}",
+                request.domain
+            ));
+        };
+        if entry.request.domain != request.domain || entry.request.payload != request.payload {
+            return Err(format!(
+                "trace diverged, expected request on domain {:#x} with payload {} but got domain {:#x} with payload {}",
+                entry.request.domain, entry.request.payload, request.domain, request.payload
+            ));
+        }
+        Ok(entries.pop_front().unwrap())
+    }
+
+    /// Number of recorded exchanges that were not replayed yet
+    pub fn remaining(&self) -> usize {
+        self.entries.lock().unwrap().len()
+    }
+}
@@ -0,0 +1,399 @@
+//! JSON-RPC 2.0 interface at `POST /rpc`, mirroring the REST routes.
+//!
+//! Every REST operation is a method taking its parameters by name or by position, binary
+//! values are base64 encoded both ways. The calls of a batch run one after the other in their
+//! order, each one checked against the credentials and body limit of the route it mirrors.
+//! A failed operation is answered with [`SERVER_ERROR`] and the status the route would have
+//! answered with in `data.status`.
+
+use std::fmt;
+
+use actix_web::http::StatusCode;
+use actix_web::web::Bytes;
+use actix_web::HttpRequest;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use serde_json::{json, Map, Value};
+use tracing::Instrument;
+
+use crate::auth::{self, Denied, RouteGroup};
+use crate::config::Config;
+use crate::ipfs::Ipfs;
+use crate::operations::{self, ServiceError};
+use crate::rollup::client::{RollupClient, RollupError};
+
+/// The request is not valid JSON
+pub const PARSE_ERROR: i64 = -32700;
+/// The request is not a valid JSON-RPC request or the batch is empty
+pub const INVALID_REQUEST: i64 = -32600;
+pub const METHOD_NOT_FOUND: i64 = -32601;
+pub const INVALID_PARAMS: i64 = -32602;
+/// The operation failed or was refused
+pub const SERVER_ERROR: i64 = -32000;
+
+/// Method of the interface, one per REST operation
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Method {
+    StateGet,
+    StateSet,
+    StateDelete,
+    StateOpen,
+    StateCommit,
+    MetadataGet,
+    DataGet,
+    IpfsGet,
+    IpfsPut,
+    Hint,
+    AppGet,
+}
+
+impl Method {
+    fn parse(name: &str) -> Option<Self> {
+        match name {
+            "state_get" => Some(Method::StateGet),
+            "state_set" => Some(Method::StateSet),
+            "state_delete" => Some(Method::StateDelete),
+            "state_open" => Some(Method::StateOpen),
+            "state_commit" => Some(Method::StateCommit),
+            "metadata_get" => Some(Method::MetadataGet),
+            "data_get" => Some(Method::DataGet),
+            "ipfs_get" => Some(Method::IpfsGet),
+            "ipfs_put" => Some(Method::IpfsPut),
+            "hint" => Some(Method::Hint),
+            "app_get" => Some(Method::AppGet),
+            _ => None,
+        }
+    }
+
+    /// Names of the parameters, in the order they are given by position
+    fn params(self) -> &'static [&'static str] {
+        match self {
+            Method::StateGet | Method::StateDelete => &["key"],
+            Method::StateSet => &["key", "value"],
+            Method::StateOpen | Method::StateCommit | Method::AppGet => &[],
+            Method::MetadataGet => &["text"],
+            Method::DataGet => &["namespace", "data_id"],
+            Method::IpfsGet => &["cid"],
+            Method::IpfsPut => &["block"],
+            Method::Hint => &["what"],
+        }
+    }
+
+    /// Pattern of the REST route doing the same, which sets the credentials and body limit
+    fn route(self) -> &'static str {
+        match self {
+            Method::StateGet => "/get_state/{key}",
+            Method::StateSet => "/set_state/{key}",
+            Method::StateDelete => "/delete_state/{key}",
+            Method::StateOpen => "/open_state",
+            Method::StateCommit => "/commit_state",
+            Method::MetadataGet => "/metadata/{text}",
+            Method::DataGet => "/get_data/{namespace}/{data_id}",
+            Method::IpfsGet => "/ipfs/get/{cid}",
+            Method::IpfsPut => "/ipfs/put/{cid}",
+            Method::Hint => "/hint/{what}",
+            Method::AppGet => "/get_app",
+        }
+    }
+}
+
+/// Error object of a response
+#[derive(Debug)]
+struct RpcError {
+    code: i64,
+    message: String,
+    data: Option<Value>,
+}
+
+impl RpcError {
+    fn new(code: i64, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+            data: None,
+        }
+    }
+
+    /// Refused or failed operation, answered by the REST route with `status`
+    fn server(message: String, status: StatusCode) -> Self {
+        Self {
+            code: SERVER_ERROR,
+            message,
+            data: Some(json!({ "status": status.as_u16() })),
+        }
+    }
+
+    fn too_large(what: &str, limit: usize) -> Self {
+        Self {
+            code: SERVER_ERROR,
+            message: format!("{} exceeds the limit of {} bytes", what, limit),
+            data: Some(json!({
+                "status": StatusCode::PAYLOAD_TOO_LARGE.as_u16(),
+                "limit": limit,
+            })),
+        }
+    }
+
+    fn denied(denied: Denied) -> Self {
+        let status = match denied {
+            Denied::Missing(_) => StatusCode::UNAUTHORIZED,
+            Denied::Invalid(_) => StatusCode::FORBIDDEN,
+        };
+        Self::server(denied.reason().to_string(), status)
+    }
+
+    fn service(method: &str, e: ServiceError) -> Self {
+        log::error!("failed to handle {} call: {}", method, e);
+        if let ServiceError::Rollup(RollupError::PayloadTooLarge { limit, .. }) = e {
+            return Self::too_large(&format!("GIO payload of {}", method), limit);
+        }
+        Self::server(
+            format!("Failed to handle {} call: {}", method, e),
+            e.status(),
+        )
+    }
+
+    fn to_json(&self) -> Value {
+        let mut error = json!({ "code": self.code, "message": self.message });
+        if let Some(data) = &self.data {
+            error["data"] = data.clone();
+        }
+        error
+    }
+}
+
+/// What the calls of a request share
+struct Context<'a> {
+    req: &'a HttpRequest,
+    /// Whole body of the request, signed requests are checked against it
+    body: &'a [u8],
+    config: &'a Config,
+    rollup: &'a RollupClient,
+    ipfs: &'a Ipfs,
+}
+
+fn response(id: Value, result: Result<Value, RpcError>) -> Value {
+    match result {
+        Ok(result) => json!({ "jsonrpc": "2.0", "result": result, "id": id }),
+        Err(error) => json!({ "jsonrpc": "2.0", "error": error.to_json(), "id": id }),
+    }
+}
+
+/// Run the single request or the batch in `body`, `None` when it only holds notifications
+pub async fn handle(
+    req: &HttpRequest,
+    body: &Bytes,
+    config: &Config,
+    rollup: &RollupClient,
+    ipfs: &Ipfs,
+) -> Option<Value> {
+    let request: Value = match serde_json::from_slice(body) {
+        Ok(request) => request,
+        Err(e) => {
+            let error = RpcError::new(PARSE_ERROR, format!("Parse error: {}", e));
+            return Some(response(Value::Null, Err(error)));
+        }
+    };
+    let context = Context {
+        req,
+        body,
+        config,
+        rollup,
+        ipfs,
+    };
+    match request {
+        Value::Array(calls) if calls.is_empty() => {
+            let error = RpcError::new(INVALID_REQUEST, "Invalid Request: empty batch");
+            Some(response(Value::Null, Err(error)))
+        }
+        Value::Array(calls) => {
+            let mut responses = Vec::with_capacity(calls.len());
+            for call in calls {
+                responses.extend(run(&context, call).await);
+            }
+            (!responses.is_empty()).then_some(Value::Array(responses))
+        }
+        call => run(&context, call).await,
+    }
+}
+
+/// Run one request, `None` for a notification
+async fn run(context: &Context<'_>, request: Value) -> Option<Value> {
+    let Value::Object(mut request) = request else {
+        let error = RpcError::new(INVALID_REQUEST, "Invalid Request: not an object");
+        return Some(response(Value::Null, Err(error)));
+    };
+    let id = request.remove("id");
+    let valid_id = match &id {
+        None => true,
+        Some(id) => id.is_string() || id.is_number() || id.is_null(),
+    };
+    if !valid_id {
+        let error = RpcError::new(INVALID_REQUEST, "Invalid Request: id is not valid");
+        return Some(response(Value::Null, Err(error)));
+    }
+    let method = match (request.get("jsonrpc"), request.get("method")) {
+        (Some(Value::String(version)), Some(Value::String(method))) if version == "2.0" => {
+            method.clone()
+        }
+        _ => {
+            let error = RpcError::new(
+                INVALID_REQUEST,
+                "Invalid Request: jsonrpc should be \"2.0\" and method a string",
+            );
+            return Some(response(id.unwrap_or_default(), Err(error)));
+        }
+    };
+    let span = tracing::info_span!(
+        "rpc_call",
+        method = %method,
+        error = tracing::field::Empty,
+    );
+    let result = call(context, &method, request.remove("params"))
+        .instrument(span.clone())
+        .await;
+    if let Err(error) = &result {
+        span.record("error", error.message.as_str());
+    }
+    // Notifications are run without being answered, even when they fail
+    id.map(|id| response(id, result))
+}
+
+async fn call(context: &Context<'_>, name: &str, params: Option<Value>) -> Result<Value, RpcError> {
+    let method = Method::parse(name)
+        .ok_or_else(|| RpcError::new(METHOD_NOT_FOUND, format!("Method not found: {}", name)))?;
+    let params = Params::new(method, params)?;
+    if let Some(group) = RouteGroup::of(method.route()) {
+        auth::verify(context.req, context.body, &context.config.auth, group)
+            .map_err(RpcError::denied)?;
+    }
+
+    let (config, rollup, ipfs) = (context.config, context.rollup, context.ipfs);
+    let result = match method {
+        Method::StateGet => operations::read_state(ipfs, params.key("key")?)
+            .await
+            .map(encode),
+        Method::StateSet => {
+            let value = params.bytes("value", limit(config, method))?;
+            operations::write_state(ipfs, params.key("key")?, value)
+                .await
+                .map(|()| Value::Null)
+        }
+        Method::StateDelete => operations::delete_state(ipfs, params.key("key")?)
+            .await
+            .map(|()| Value::Null),
+        Method::StateOpen => operations::open_state(rollup, ipfs)
+            .await
+            .map(|()| Value::Null),
+        Method::StateCommit => operations::commit_state(rollup, ipfs)
+            .await
+            .map(|()| Value::Null),
+        Method::MetadataGet => operations::metadata(rollup, params.string("text")?)
+            .await
+            .map(encode),
+        Method::DataGet => operations::data(
+            rollup,
+            params.string("namespace")?,
+            params.string("data_id")?,
+        )
+        .await
+        .map(encode),
+        Method::IpfsGet => operations::ipfs_get(rollup, params.string("cid")?)
+            .await
+            .map(encode),
+        Method::IpfsPut => {
+            let block = params.bytes("block", limit(config, method))?;
+            operations::ipfs_put(rollup, block).await.map(encode)
+        }
+        Method::Hint => {
+            let what = params.string("what")?;
+            if what.len() > config.hint_limit() {
+                return Err(RpcError::too_large("hint", config.hint_limit()));
+            }
+            operations::hint(rollup, what).await.map(encode)
+        }
+        Method::AppGet => operations::get_app(config, rollup, ipfs)
+            .await
+            .map(|cid| Value::String(cid.to_string())),
+    };
+    result.map_err(|e| RpcError::service(name, e))
+}
+
+/// Name and limit of the decoded value of `method`, the body limit of its route
+fn limit(config: &Config, method: Method) -> (&'static str, usize) {
+    config
+        .body_limit(method.route())
+        .unwrap_or(("rpc", config.body_limits.rpc))
+}
+
+fn encode(bytes: impl AsRef<[u8]>) -> Value {
+    Value::String(BASE64.encode(bytes))
+}
+
+/// Parameters of a call, by name
+struct Params(Map<String, Value>);
+
+impl Params {
+    /// Name the parameters given by position and refuse the ones `method` does not take
+    fn new(method: Method, params: Option<Value>) -> Result<Self, RpcError> {
+        let names = method.params();
+        let params = match params {
+            None => Map::new(),
+            Some(Value::Object(params)) => params,
+            Some(Value::Array(params)) if params.len() <= names.len() => names
+                .iter()
+                .map(|name| name.to_string())
+                .zip(params)
+                .collect(),
+            Some(Value::Array(params)) => {
+                return Err(invalid_params(format!(
+                    "expected at most {} parameters, got {}",
+                    names.len(),
+                    params.len()
+                )))
+            }
+            Some(_) => return Err(invalid_params("params should be an object or an array")),
+        };
+        if let Some(unknown) = params.keys().find(|name| !names.contains(&name.as_str())) {
+            return Err(invalid_params(format!("unknown parameter {}", unknown)));
+        }
+        Ok(Self(params))
+    }
+
+    fn string(&self, name: &str) -> Result<&str, RpcError> {
+        self.0
+            .get(name)
+            .and_then(Value::as_str)
+            .ok_or_else(|| invalid_params(format!("{} should be a string", name)))
+    }
+
+    /// Name of a state entry, a single path segment as in the REST routes
+    fn key(&self, name: &str) -> Result<&str, RpcError> {
+        let key = self.string(name)?;
+        if key.is_empty() || key.contains('/') || key == "." || key == ".." {
+            return Err(invalid_params(format!(
+                "{} should be a non-empty name without /",
+                name
+            )));
+        }
+        Ok(key)
+    }
+
+    /// Base64 decoded value, refused when it is over `limit`
+    fn bytes(&self, name: &str, (route, limit): (&str, usize)) -> Result<Bytes, RpcError> {
+        let bytes = BASE64
+            .decode(self.string(name)?)
+            .map_err(|e| invalid_params(format!("{} is not valid base64: {}", name, e)))?;
+        if bytes.len() > limit {
+            return Err(RpcError::too_large(
+                &format!("{} of {}", name, route),
+                limit,
+            ));
+        }
+        Ok(Bytes::from(bytes))
+    }
+}
+
+fn invalid_params(reason: impl fmt::Display) -> RpcError {
+    RpcError::new(INVALID_PARAMS, format!("Invalid params: {}", reason))
+}
@@ -0,0 +1,75 @@
+//! Termination signals and their forwarding to child processes.
+
+use std::time::Duration;
+
+use tokio::process::Child;
+
+#[cfg(unix)]
+pub const SIGINT: i32 = libc::SIGINT;
+#[cfg(unix)]
+pub const SIGTERM: i32 = libc::SIGTERM;
+#[cfg(not(unix))]
+pub const SIGINT: i32 = 2;
+#[cfg(not(unix))]
+pub const SIGTERM: i32 = 15;
+
+/// Wait for SIGTERM or SIGINT and return the signal number received
+#[cfg(unix)]
+pub async fn signal() -> std::io::Result<i32> {
+    use tokio::signal::unix::{signal, SignalKind};
+    let mut terminate = signal(SignalKind::terminate())?;
+    let mut interrupt = signal(SignalKind::interrupt())?;
+    tokio::select! {
+        _ = terminate.recv() => Ok(SIGTERM),
+        _ = interrupt.recv() => Ok(SIGINT),
+    }
+}
+
+/// Wait for Ctrl-C, reported as SIGINT
+#[cfg(not(unix))]
+pub async fn signal() -> std::io::Result<i32> {
+    tokio::signal::ctrl_c().await?;
+    Ok(SIGINT)
+}
+
+#[cfg(unix)]
+fn send_signal(child: &mut Child, signal: i32) {
+    if let Some(pid) = child.id() {
+        // SAFETY: kill has no memory safety requirements, the pid belongs to our child
+        if unsafe { libc::kill(pid as libc::pid_t, signal) } != 0 {
+            log::warn!(
+                "failed to send signal {} to {}: {}",
+                signal,
+                pid,
+                std::io::Error::last_os_error()
+            );
+        }
+    }
+}
+
+#[cfg(not(unix))]
+fn send_signal(child: &mut Child, _signal: i32) {
+    let _ = child.start_kill();
+}
+
+/// Forward `signal` to the child and give it `grace` to exit before killing it.
+/// Returns the exit status and whether the child had to be killed.
+pub async fn terminate(
+    child: &mut Child,
+    signal: i32,
+    grace: Duration,
+) -> (std::io::Result<std::process::ExitStatus>, bool) {
+    send_signal(child, signal);
+    match tokio::time::timeout(grace, child.wait()).await {
+        Ok(result) => (result, false),
+        Err(_) => {
+            log::warn!(
+                "process {:?} did not exit within {:?}, killing it",
+                child.id(),
+                grace
+            );
+            let _ = child.kill().await;
+            (child.wait().await, true)
+        }
+    }
+}
@@ -0,0 +1,264 @@
+//! Logs and tracing spans of salsa.
+//!
+//! Every HTTP request runs in an `http_request` span carrying its request id, the GIO and
+//! IPFS calls it triggers get child spans. Finished spans can be written to a file in the
+//! OTLP/JSON format, one export request per line, to be loaded offline.
+
+use std::collections::hash_map::RandomState;
+use std::fmt;
+use std::fs::{File, OpenOptions};
+use std::hash::{BuildHasher, Hasher};
+use std::io::Write;
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use tracing::field::{Field, Visit};
+use tracing::span::{Attributes, Id, Record};
+use tracing::Subscriber;
+use tracing_subscriber::layer::{Context, SubscriberExt};
+use tracing_subscriber::registry::LookupSpan;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{EnvFilter, Layer};
+
+/// Header carrying the id of a request, taken from the client when it sends one
+pub const REQUEST_ID_HEADER: &str = "x-request-id";
+
+/// Span field telling the OTLP span kind, `server` or `client`
+const KIND_FIELD: &str = "otel.kind";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    Text,
+    /// One JSON object per line, with the fields of the enclosing spans
+    Json,
+}
+
+impl std::str::FromStr for LogFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "text" => Ok(LogFormat::Text),
+            "json" => Ok(LogFormat::Json),
+            _ => Err(format!("unknown log format {}, expected text or json", s)),
+        }
+    }
+}
+
+/// Install the global subscriber, `log` records are forwarded to it. `RUST_LOG` overrides
+/// `level` when set.
+pub fn init(level: &str, format: LogFormat, trace_file: Option<&Path>) -> std::io::Result<()> {
+    let filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new(level));
+    let output = match format {
+        LogFormat::Text => tracing_subscriber::fmt::layer().without_time().boxed(),
+        LogFormat::Json => tracing_subscriber::fmt::layer()
+            .json()
+            .with_current_span(false)
+            .with_span_list(true)
+            .boxed(),
+    };
+    let exporter = trace_file.map(OtlpFileLayer::create).transpose()?;
+    tracing_subscriber::registry()
+        .with(filter)
+        .with(output)
+        .with(exporter)
+        .try_init()
+        .map_err(std::io::Error::other)
+}
+
+/// Random id of `bytes` bytes, hex encoded
+fn random_id(bytes: usize) -> String {
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+    let mut id = String::with_capacity(bytes * 2);
+    while id.len() < bytes * 2 {
+        let mut hasher = RandomState::new().build_hasher();
+        hasher.write_u64(COUNTER.fetch_add(1, Ordering::Relaxed));
+        id.push_str(&format!("{:016x}", hasher.finish()));
+    }
+    id.truncate(bytes * 2);
+    id
+}
+
+/// New id for a request that did not come with one
+pub fn request_id() -> String {
+    random_id(16)
+}
+
+fn unix_nanos(time: SystemTime) -> String {
+    time.duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_nanos()
+        .to_string()
+}
+
+/// Span as it is exported, filled while the span is open
+struct SpanRecord {
+    trace_id: String,
+    span_id: String,
+    parent_span_id: String,
+    kind: u8,
+    start: SystemTime,
+    attributes: Vec<(String, Value)>,
+}
+
+impl Visit for SpanRecord {
+    fn record_i64(&mut self, field: &Field, value: i64) {
+        self.set(field, json!({ "intValue": value.to_string() }));
+    }
+
+    fn record_u64(&mut self, field: &Field, value: u64) {
+        self.set(field, json!({ "intValue": value.to_string() }));
+    }
+
+    fn record_f64(&mut self, field: &Field, value: f64) {
+        self.set(field, json!({ "doubleValue": value }));
+    }
+
+    fn record_bool(&mut self, field: &Field, value: bool) {
+        self.set(field, json!({ "boolValue": value }));
+    }
+
+    fn record_str(&mut self, field: &Field, value: &str) {
+        if field.name() == KIND_FIELD {
+            // SPAN_KIND_SERVER and SPAN_KIND_CLIENT
+            self.kind = match value {
+                "server" => 2,
+                "client" => 3,
+                _ => 1,
+            };
+            return;
+        }
+        self.set(field, json!({ "stringValue": value }));
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        self.record_str(field, &format!("{:?}", value));
+    }
+}
+
+impl SpanRecord {
+    fn set(&mut self, field: &Field, value: Value) {
+        let name = field.name();
+        match self.attributes.iter_mut().find(|(key, _)| key == name) {
+            Some((_, current)) => *current = value,
+            None => self.attributes.push((name.to_string(), value)),
+        }
+    }
+
+    fn to_otlp(&self, name: &str, end: SystemTime) -> Value {
+        // STATUS_CODE_ERROR when the span recorded an error
+        let status = if self.attributes.iter().any(|(key, _)| key == "error") {
+            json!({ "code": 2 })
+        } else {
+            json!({})
+        };
+        let attributes: Vec<Value> = self
+            .attributes
+            .iter()
+            .map(|(key, value)| json!({ "key": key, "value": value }))
+            .collect();
+        json!({
+            "resourceSpans": [{
+                "resource": {
+                    "attributes": [
+                        { "key": "service.name", "value": { "stringValue": "salsa" } },
+                        {
+                            "key": "service.version",
+                            "value": { "stringValue": env!("CARGO_PKG_VERSION") }
+                        },
+                    ]
+                },
+                "scopeSpans": [{
+                    "scope": { "name": "salsa" },
+                    "spans": [{
+                        "traceId": self.trace_id,
+                        "spanId": self.span_id,
+                        "parentSpanId": self.parent_span_id,
+                        "name": name,
+                        "kind": self.kind,
+                        "startTimeUnixNano": unix_nanos(self.start),
+                        "endTimeUnixNano": unix_nanos(end),
+                        "attributes": attributes,
+                        "status": status,
+                    }]
+                }]
+            }]
+        })
+    }
+}
+
+/// Write every finished span to a file in the OTLP/JSON format, as the file exporter of the
+/// OpenTelemetry collector does
+pub struct OtlpFileLayer {
+    file: Mutex<File>,
+}
+
+impl OtlpFileLayer {
+    /// Append to the file at `path`, creating it if needed
+    pub fn create(path: &Path) -> std::io::Result<Self> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        Ok(Self {
+            file: Mutex::new(file),
+        })
+    }
+}
+
+impl<S> Layer<S> for OtlpFileLayer
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn on_new_span(&self, attrs: &Attributes<'_>, id: &Id, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(id) else {
+            return;
+        };
+        let parent = span.parent().and_then(|parent| {
+            let extensions = parent.extensions();
+            extensions
+                .get::<SpanRecord>()
+                .map(|record| (record.trace_id.clone(), record.span_id.clone()))
+        });
+        let (trace_id, parent_span_id) = parent.unwrap_or_else(|| (random_id(16), String::new()));
+        let mut record = SpanRecord {
+            trace_id,
+            span_id: random_id(8),
+            parent_span_id,
+            // SPAN_KIND_INTERNAL
+            kind: 1,
+            start: SystemTime::now(),
+            attributes: Vec::new(),
+        };
+        attrs.record(&mut record);
+        span.extensions_mut().insert(record);
+    }
+
+    fn on_record(&self, id: &Id, values: &Record<'_>, ctx: Context<'_, S>) {
+        if let Some(span) = ctx.span(id) {
+            if let Some(record) = span.extensions_mut().get_mut::<SpanRecord>() {
+                values.record(record);
+            }
+        }
+    }
+
+    fn on_close(&self, id: Id, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(&id) else {
+            return;
+        };
+        let Some(record) = span.extensions_mut().remove::<SpanRecord>() else {
+            return;
+        };
+        let mut line = record
+            .to_otlp(span.name(), SystemTime::now())
+            .to_string()
+            .into_bytes();
+        line.push(b'\n');
+        if let Ok(mut file) = self.file.lock() {
+            // Logging from inside the subscriber would re-enter it
+            let _ = file.write_all(&line);
+        }
+    }
+}
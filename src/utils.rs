@@ -1,3 +1,8 @@
+use std::pin::Pin;
+
+use actix_web::dev::{Payload, ServiceRequest};
+use actix_web::error::PayloadError;
+use futures::{Stream, StreamExt};
 use http_body_util::{BodyExt, Full};
 use hyper::body::{Body, Bytes};
 use hyper::http::Response;
@@ -24,3 +29,27 @@ where
 {
     Ok(response.into_body().collect().await?.to_bytes())
 }
+
+/// Read the whole body of `req`, `None` as soon as it grows over `limit` bytes
+pub async fn read_body(
+    req: &mut ServiceRequest,
+    limit: usize,
+) -> Result<Option<Bytes>, PayloadError> {
+    let mut payload = req.parts_mut().1.take();
+    let mut body = actix_web::web::BytesMut::new();
+    while let Some(chunk) = payload.next().await {
+        let chunk = chunk?;
+        if body.len() + chunk.len() > limit {
+            return Ok(None);
+        }
+        body.extend_from_slice(&chunk);
+    }
+    Ok(Some(body.freeze()))
+}
+
+/// Hand a body read by a middleware back to the request, for the handler to extract
+pub fn restore_body(req: &mut ServiceRequest, body: Bytes) {
+    let stream: Pin<Box<dyn Stream<Item = Result<Bytes, PayloadError>>>> =
+        Box::pin(futures::stream::once(async move { Ok(body) }));
+    req.set_payload(Payload::from(stream));
+}
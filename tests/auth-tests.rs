@@ -0,0 +1,184 @@
+// Copyright Cartesi and individual authors (see AUTHORS)
+// SPDX-License-Identifier: Apache-2.0
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+mod common;
+
+#[cfg(test)]
+mod tests {
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    use crate::common::{start_with, Context, HOST};
+    use http_body_util::{Empty, Full};
+    use hyper::{body::Bytes, Method, StatusCode};
+    use salsa::auth::{self, SIGNATURE_HEADER, TIMESTAMP_HEADER};
+    use salsa::config::{AuthConfig, AuthMethod, Config, Secret};
+    use salsa::utils;
+
+    const TOKEN: &str = "debug-token";
+    const SECRET: &str = "debug-secret";
+    const EXCEPTION: &str = r#"{"kind": "invalid_input", "message": "bad deposit"}"#;
+
+    /// Start a mock rollup server and a salsa instance requiring a bearer token on the
+    /// mutating routes and signed requests on the GIO ones
+    fn start(mock_port: u16, salsa_port: u16) -> std::io::Result<Context> {
+        let config = Config {
+            // Nothing listens there, mutating routes fail once let through
+            ipfs_url: format!("http://{}:1", HOST),
+            auth: AuthConfig {
+                mutate_state: Some(AuthMethod::Bearer(Secret::new(TOKEN))),
+                gio: Some(AuthMethod::Hmac(Secret::new(SECRET))),
+                ..AuthConfig::default()
+            },
+            ..Config::new()
+        };
+        start_with(mock_port, salsa_port, config)
+    }
+
+    async fn request(
+        request: hyper::http::request::Builder,
+        body: &'static str,
+    ) -> Result<hyper::Response<hyper::body::Incoming>, Box<dyn std::error::Error>> {
+        let client = utils::create_client();
+        Ok(client
+            .request(request.body(Full::new(Bytes::from_static(body.as_bytes())))?)
+            .await?)
+    }
+
+    fn now() -> u64 {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs()
+    }
+
+    #[tokio::test]
+    async fn test_bearer_token() -> Result<(), Box<dyn std::error::Error>> {
+        let context = start(5624, 5625)?;
+        let url = format!("http://{}:5625/delete_state/key", HOST);
+
+        let res = request(hyper::Request::delete(&url), "").await?;
+        assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+        assert_eq!(res.headers()["www-authenticate"], "Bearer");
+
+        let res = request(
+            hyper::Request::delete(&url).header("authorization", "Bearer wrong-token"),
+            "",
+        )
+        .await?;
+        assert_eq!(res.status(), StatusCode::FORBIDDEN);
+
+        let res = request(
+            hyper::Request::delete(&url).header("authorization", format!("Bearer {}", TOKEN)),
+            "",
+        )
+        .await?;
+        assert!(
+            ![StatusCode::UNAUTHORIZED, StatusCode::FORBIDDEN].contains(&res.status()),
+            "{}",
+            res.status()
+        );
+
+        // Groups without credentials stay open
+        let client = utils::create_client();
+        let req = hyper::Request::builder()
+            .uri(format!("http://{}:5625/health", HOST))
+            .body(Empty::<Bytes>::new())?;
+        assert_eq!(client.request(req).await?.status(), StatusCode::OK);
+
+        // Secrets are not reported
+        let req = hyper::Request::builder()
+            .uri(format!("http://{}:5625/version", HOST))
+            .body(Empty::<Bytes>::new())?;
+        let body = utils::response_to_bytes(client.request(req).await?).await?;
+        let version = String::from_utf8(body.to_vec())?;
+        assert!(
+            !version.contains(TOKEN) && !version.contains(SECRET),
+            "{}",
+            version
+        );
+        assert!(version.contains("<redacted>"), "{}", version);
+
+        context.stop().await;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_hmac_signature() -> Result<(), Box<dyn std::error::Error>> {
+        let context = start(5634, 5635)?;
+        let mock = &context.mock;
+        let url = format!("http://{}:5635/exception", HOST);
+        let signed = |timestamp: u64, body: &str| {
+            hyper::Request::builder()
+                .method(Method::POST)
+                .uri(&url)
+                .header(hyper::header::CONTENT_TYPE, "application/json")
+                .header(TIMESTAMP_HEADER, timestamp.to_string())
+                .header(
+                    SIGNATURE_HEADER,
+                    auth::sign(SECRET, timestamp, "POST", "/exception", body.as_bytes()),
+                )
+        };
+
+        let unsigned = hyper::Request::builder()
+            .method(Method::POST)
+            .uri(&url)
+            .header(hyper::header::CONTENT_TYPE, "application/json");
+        let res = request(unsigned, EXCEPTION).await?;
+        assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+        assert_eq!(res.headers()["www-authenticate"], "HMAC-SHA256");
+
+        // Body changed after signing
+        let res = request(signed(now(), "{}"), EXCEPTION).await?;
+        assert_eq!(res.status(), StatusCode::FORBIDDEN);
+
+        // Signed too long ago
+        let res = request(signed(now() - 3600, EXCEPTION), EXCEPTION).await?;
+        assert_eq!(res.status(), StatusCode::FORBIDDEN);
+        assert!(mock.exceptions().is_empty());
+
+        // The handler still gets the body that was read to check the signature
+        let res = request(signed(now(), EXCEPTION), EXCEPTION).await?;
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(mock.exceptions().len(), 1);
+
+        // Bodies of routes without a limit of their own are bounded by the default one, and
+        // only read once the headers are checked
+        let large: &'static str = "x".repeat(128 * 1024).leak();
+        let url = format!("http://{}:5635/metadata/name", HOST);
+        let signed = |timestamp: u64| {
+            hyper::Request::builder()
+                .uri(&url)
+                .header(TIMESTAMP_HEADER, timestamp.to_string())
+                .header(
+                    SIGNATURE_HEADER,
+                    auth::sign(SECRET, timestamp, "GET", "/metadata/name", large.as_bytes()),
+                )
+        };
+        let res = request(hyper::Request::builder().uri(&url), large).await?;
+        assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+        let res = request(signed(now() - 3600), large).await?;
+        assert_eq!(res.status(), StatusCode::FORBIDDEN);
+        let res = request(signed(now()), large).await?;
+        assert_eq!(res.status(), StatusCode::PAYLOAD_TOO_LARGE);
+        let error: serde_json::Value =
+            serde_json::from_slice(&utils::response_to_bytes(res).await?)?;
+        assert_eq!(error["limit"], 64 * 1024);
+        assert!(mock.requests().is_empty());
+
+        context.stop().await;
+        Ok(())
+    }
+}
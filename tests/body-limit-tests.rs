@@ -0,0 +1,86 @@
+// Copyright Cartesi and individual authors (see AUTHORS)
+// SPDX-License-Identifier: Apache-2.0
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+mod common;
+
+#[cfg(test)]
+mod tests {
+    use crate::common::{get, start_with, HOST};
+    use http_body_util::Full;
+    use hyper::{body::Bytes, Method, StatusCode};
+    use salsa::config::{BodyLimits, Config};
+    use salsa::rollup::client::{RollupClient, RollupError};
+    use salsa::rollup::HINT;
+    use salsa::utils;
+
+    #[tokio::test]
+    async fn test_body_limits() -> Result<(), Box<dyn std::error::Error>> {
+        let config = Config {
+            gio_max_payload: 32,
+            body_limits: BodyLimits {
+                ipfs_put: 16,
+                hint: 8,
+                ..BodyLimits::default()
+            },
+            ..Config::new()
+        };
+        let context = start_with(5484, 5485, config)?;
+        let client = utils::create_client();
+        let put = |body: &'static [u8]| {
+            hyper::Request::builder()
+                .method(Method::PUT)
+                .uri(context.salsa_address.clone() + "/ipfs/put/any")
+                .body(Full::new(Bytes::from_static(body)))
+        };
+
+        let res = client.request(put(b"sixteen bytes...")?).await?;
+        assert_eq!(res.status(), StatusCode::OK);
+        let res = client.request(put(b"seventeen bytes..")?).await?;
+        assert_eq!(res.status(), StatusCode::PAYLOAD_TOO_LARGE);
+        let error: serde_json::Value =
+            serde_json::from_slice(&utils::response_to_bytes(res).await?)?;
+        assert_eq!(error["limit"], 16);
+        assert_eq!(
+            error["error"],
+            "request body of ipfs_put exceeds the limit of 16 bytes"
+        );
+
+        let (status, _) = get(context.salsa_address.clone() + "/hint/short").await?;
+        assert_eq!(status, StatusCode::OK);
+        let (status, body) = get(context.salsa_address.clone() + "/hint/much-too-long").await?;
+        assert_eq!(status, StatusCode::PAYLOAD_TOO_LARGE);
+        assert_eq!(
+            serde_json::from_slice::<serde_json::Value>(&body)?["limit"],
+            8
+        );
+        assert_eq!(context.mock.requests().len(), 2);
+
+        context.stop().await;
+
+        // Payloads that do not fit in a GIO request are refused before yielding
+        let client = RollupClient::new(&Config {
+            rollup_url: format!("http://{}:5486", HOST),
+            gio_max_payload: 4,
+            ..Config::new()
+        })?;
+        let error = client.call(HINT, b"too large").await.unwrap_err();
+        assert!(matches!(
+            error,
+            RollupError::PayloadTooLarge { size: 9, limit: 4 }
+        ));
+        Ok(())
+    }
+}
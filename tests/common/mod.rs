@@ -0,0 +1,82 @@
+// Copyright Cartesi and individual authors (see AUTHORS)
+// SPDX-License-Identifier: Apache-2.0
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+//! Fixture shared by the integration tests, each test file only uses part of it
+#![allow(dead_code)]
+
+use actix_server::ServerHandle;
+use http_body_util::Empty;
+use hyper::{body::Bytes, Method, StatusCode};
+use salsa::config::Config;
+use salsa::mock::MockRollup;
+use salsa::{http_service, utils};
+
+pub const HOST: &str = "127.0.0.1";
+
+pub struct Context {
+    pub mock: MockRollup,
+    pub salsa_address: String,
+    pub mock_handle: ServerHandle,
+    pub salsa_handle: ServerHandle,
+}
+
+impl Context {
+    pub async fn stop(self) {
+        self.salsa_handle.stop(true).await;
+        self.mock_handle.stop(true).await;
+    }
+}
+
+/// Start a mock rollup server and a salsa instance talking to it
+pub fn start(mock_port: u16, salsa_port: u16) -> std::io::Result<Context> {
+    start_with(mock_port, salsa_port, Config::new())
+}
+
+/// Same as `start`, with the other settings taken from `config`
+pub fn start_with(mock_port: u16, salsa_port: u16, config: Config) -> std::io::Result<Context> {
+    let mock = MockRollup::new();
+    let mock_server = mock.serve(HOST, mock_port)?;
+    let mock_handle = mock_server.handle();
+    tokio::spawn(mock_server);
+
+    let config = Config {
+        http_address: HOST.to_string(),
+        http_port: salsa_port,
+        rollup_url: format!("http://{}:{}", HOST, mock_port),
+        ..config
+    };
+    let salsa_server = http_service::create_server(&config)?;
+    let salsa_handle = salsa_server.handle();
+    tokio::spawn(salsa_server);
+
+    Ok(Context {
+        mock,
+        salsa_address: format!("http://{}:{}", HOST, salsa_port),
+        mock_handle,
+        salsa_handle,
+    })
+}
+
+pub async fn get(url: String) -> Result<(StatusCode, Vec<u8>), Box<dyn std::error::Error>> {
+    let client = utils::create_client();
+    let req = hyper::Request::builder()
+        .method(Method::GET)
+        .uri(url)
+        .body(Empty::<Bytes>::new())?;
+    let res = client.request(req).await?;
+    let status = res.status();
+    Ok((status, utils::response_to_bytes(res).await?.to_vec()))
+}
@@ -0,0 +1,221 @@
+// Copyright Cartesi and individual authors (see AUTHORS)
+// SPDX-License-Identifier: Apache-2.0
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+#[cfg(test)]
+mod tests {
+    use std::path::{Path, PathBuf};
+    use std::process::Command;
+    use std::time::Duration;
+
+    use salsa::config::{Config, Listener, ReadinessProbe};
+    use salsa::telemetry::LogFormat;
+
+    fn write_config(name: &str, contents: &str) -> std::io::Result<PathBuf> {
+        let path = std::env::temp_dir().join(format!("salsa-{}-{}.toml", name, std::process::id()));
+        std::fs::write(&path, contents)?;
+        Ok(path)
+    }
+
+    fn env(vars: &[(&str, &str)]) -> Vec<(String, String)> {
+        vars.iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_load_layers_env_over_file() -> Result<(), Box<dyn std::error::Error>> {
+        let path = write_config(
+            "layers",
+            r#"
+            http_port = 6000
+            rollup_url = "http://file:5004"
+            gio_timeout = 5000
+            log_format = "json"
+            dapp_readiness = { tcp = "127.0.0.1:9000" }
+
+            [dapp_limits]
+            cpu_time = 3
+            "#,
+        )?;
+        let config = Config::load(
+            Some(&path),
+            env(&[
+                ("SALSA_ROLLUP_URL", "http://env:5004"),
+                ("SALSA_DAPP_LIMITS__OPEN_FILES", "64"),
+                // Reads as a number, kept as a string for the string field
+                ("SALSA_APP_CID", "0123"),
+                // Map keys are variable names, kept as they are
+                ("SALSA_DAPP_ENV__My_Var", "1"),
+                // Set by salsa for its dapp, not a config field
+                ("SALSA_URL", "http://127.0.0.1:5005"),
+                ("HOME", "/root"),
+            ]),
+        )?;
+        std::fs::remove_file(&path)?;
+
+        assert_eq!(config.http_port, 6000);
+        assert_eq!(config.rollup_url, "http://env:5004");
+        assert_eq!(config.ipfs_url, Config::new().ipfs_url);
+        assert_eq!(config.gio_timeout, Duration::from_secs(5));
+        assert_eq!(config.log_format, LogFormat::Json);
+        assert_eq!(
+            config.dapp_readiness,
+            Some(ReadinessProbe::Tcp("127.0.0.1:9000".to_string()))
+        );
+        assert_eq!(config.dapp_limits.cpu_time, Some(3));
+        assert_eq!(config.dapp_limits.open_files, Some(64));
+        assert_eq!(config.app_cid.as_deref(), Some("0123"));
+        assert_eq!(config.dapp_env.get("My_Var").map(String::as_str), Some("1"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_load_rejects_invalid_settings() -> Result<(), Box<dyn std::error::Error>> {
+        let path = write_config("invalid", "gio_timout = 5000\n")?;
+        let error = Config::load(Some(&path), env(&[])).unwrap_err();
+        std::fs::remove_file(&path)?;
+        assert!(error.to_string().contains("gio_timout"), "{}", error);
+        let path = write_config("invalid-limits", "[dapp_limits]\ncpu_tme = 1\n")?;
+        let error = Config::load(Some(&path), env(&[])).unwrap_err();
+        std::fs::remove_file(&path)?;
+        assert!(error.to_string().contains("cpu_tme"), "{}", error);
+
+        // Secrets printed by config print are not loaded back as the placeholder
+        let path = write_config("redacted", "[auth]\ngio = { hmac = \"<redacted>\" }\n")?;
+        let error = Config::load(Some(&path), env(&[])).unwrap_err();
+        std::fs::remove_file(&path)?;
+        assert!(error.to_string().contains("<redacted>"), "{}", error);
+
+        let error = Config::load(None, env(&[("SALSA_HTTP_PORT", "port")])).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "SALSA_HTTP_PORT is not valid: invalid type: string \"port\", expected u16"
+        );
+
+        let error = Config::load(
+            None,
+            env(&[
+                ("SALSA_GIO_RECORD", "/tmp/record.jsonl"),
+                ("SALSA_GIO_REPLAY", "/tmp/replay.jsonl"),
+            ]),
+        )
+        .unwrap_err();
+        assert!(error.to_string().contains("together"), "{}", error);
+        Ok(())
+    }
+
+    #[test]
+    fn test_config_print() -> Result<(), Box<dyn std::error::Error>> {
+        let path = write_config(
+            "print",
+            "http_port = 6000\nrollup_url = \"http://file:5004\"\nipfs_url = \"http://file:5001\"\n",
+        )?;
+        let output = Command::new(env!("CARGO_BIN_EXE_salsa"))
+            .args(["config", "print", "--config"])
+            .arg(&path)
+            .args(["--ipfs-url", "http://cli:5001", "--verbose"])
+            .env("SALSA_IPFS_URL", "http://env:5001")
+            .env("SALSA_ROLLUP_URL", "http://env:5004")
+            .output()?;
+        std::fs::remove_file(&path)?;
+        assert!(output.status.success(), "{:?}", output);
+
+        // The printed configuration is a valid config file
+        let printed = write_config("printed", std::str::from_utf8(&output.stdout)?)?;
+        let config = Config::load(Some(Path::new(&printed)), env(&[]))?;
+        std::fs::remove_file(&printed)?;
+        assert_eq!(config.http_port, 6000);
+        assert_eq!(config.rollup_url, "http://env:5004");
+        assert_eq!(config.ipfs_url, "http://cli:5001");
+        assert_eq!(config.log_level, log::LevelFilter::Debug);
+        Ok(())
+    }
+
+    #[test]
+    fn test_invalid_option_values() -> Result<(), Box<dyn std::error::Error>> {
+        for (option, value) in [
+            ("--gio-timeout", "abc"),
+            ("--max-restarts", "-1"),
+            ("--dapp-stderr-level", "loud"),
+            ("--limit-open-files", "many"),
+        ] {
+            let output = Command::new(env!("CARGO_BIN_EXE_salsa"))
+                .args(["config", "print", option, value])
+                .output()?;
+            assert!(!output.status.success(), "{:?}", output);
+            let stderr = String::from_utf8(output.stderr)?;
+            assert!(
+                stderr.contains(&format!("{} '{}' is not valid", option, value)),
+                "{}",
+                stderr
+            );
+            assert!(!stderr.contains("panicked"), "{}", stderr);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_listener_addresses() {
+        for (address, expected, url) in [
+            (
+                "127.0.0.1:5005",
+                Listener::Tcp {
+                    address: "127.0.0.1".to_string(),
+                    port: 5005,
+                },
+                "http://127.0.0.1:5005",
+            ),
+            (
+                "[::1]:5005",
+                Listener::Tcp {
+                    address: "::1".to_string(),
+                    port: 5005,
+                },
+                "http://[::1]:5005",
+            ),
+            (
+                "unix:/run/salsa.sock",
+                Listener::Unix(PathBuf::from("/run/salsa.sock")),
+                "unix:/run/salsa.sock",
+            ),
+        ] {
+            let listener: Listener = address.parse().unwrap();
+            assert_eq!(listener, expected);
+            assert_eq!(listener.to_string(), address);
+            assert_eq!(listener.url(), url);
+        }
+        for invalid in [
+            "::1:5005",
+            "[::1]5005",
+            "[localhost]:5005",
+            "127.0.0.1",
+            ":5005",
+            "unix:",
+        ] {
+            assert!(invalid.parse::<Listener>().is_err(), "{} parsed", invalid);
+        }
+
+        let config = Config {
+            listen: vec![
+                "unix:/run/salsa.sock".parse().unwrap(),
+                "[::1]:6000".parse().unwrap(),
+            ],
+            ..Config::new()
+        };
+        assert_eq!(config.salsa_url(), "http://[::1]:6000");
+        assert_eq!(Config::new().salsa_url(), "http://127.0.0.1:5005");
+    }
+}
@@ -0,0 +1,135 @@
+// Copyright Cartesi and individual authors (see AUTHORS)
+// SPDX-License-Identifier: Apache-2.0
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+mod common;
+
+#[cfg(test)]
+mod tests {
+    use crate::common::{start, start_with};
+    use http_body_util::Empty;
+    use hyper::{body::Bytes, Method, StatusCode};
+    use salsa::config::{AuthConfig, AuthMethod, Config, CorsConfig, Secret};
+    use salsa::{http_service, utils};
+
+    #[tokio::test]
+    async fn test_cors_and_head() -> Result<(), Box<dyn std::error::Error>> {
+        let config = Config {
+            auth: AuthConfig {
+                read_state: Some(AuthMethod::Bearer(Secret::new("token"))),
+                ..AuthConfig::default()
+            },
+            cors: Some(CorsConfig {
+                allowed_origins: vec!["http://localhost:3000".to_string()],
+                ..CorsConfig::default()
+            }),
+            ..Config::new()
+        };
+        let context = start_with(5494, 5495, config)?;
+        let cid = cid::Cid::new_v1(
+            0x55,
+            cid::multihash::Multihash::wrap(
+                0x12,
+                &<sha2::Sha256 as sha2::Digest>::digest(b"some block"),
+            )?,
+        );
+        context.mock.add_block(&cid, b"some block");
+        let url = format!("{}/ipfs/get/{}", context.salsa_address, cid);
+        let client = utils::create_client();
+
+        // Preflights are answered before the credentials are checked
+        let req = hyper::Request::builder()
+            .method(Method::OPTIONS)
+            .uri(&url)
+            .header("origin", "http://localhost:3000")
+            .header("access-control-request-method", "GET")
+            .header("access-control-request-headers", "authorization")
+            .body(Empty::<Bytes>::new())?;
+        let res = client.request(req).await?;
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(
+            res.headers()["access-control-allow-origin"],
+            "http://localhost:3000"
+        );
+        assert_eq!(res.headers()["access-control-max-age"], "3600");
+
+        let req = hyper::Request::builder()
+            .method(Method::GET)
+            .uri(&url)
+            .header("origin", "http://localhost:3000")
+            .header("authorization", "Bearer token")
+            .body(Empty::<Bytes>::new())?;
+        let res = client.request(req).await?;
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(res.headers()["content-type"], "application/octet-stream");
+        assert_eq!(res.headers()["content-length"], "10");
+        assert_eq!(
+            res.headers()["access-control-allow-origin"],
+            "http://localhost:3000"
+        );
+        assert!(res.headers()["access-control-expose-headers"]
+            .to_str()?
+            .contains("content-length"));
+
+        // HEAD tells the length without the body
+        let req = hyper::Request::builder()
+            .method(Method::HEAD)
+            .uri(&url)
+            .header("authorization", "Bearer token")
+            .body(Empty::<Bytes>::new())?;
+        let res = client.request(req).await?;
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(res.headers()["content-length"], "10");
+        assert!(utils::response_to_bytes(res).await?.is_empty());
+        context.stop().await;
+
+        // Without a cors table no CORS headers are sent
+        let context = start(5496, 5497)?;
+        let req = hyper::Request::builder()
+            .method(Method::GET)
+            .uri(context.salsa_address.clone() + "/health")
+            .header("origin", "http://localhost:3000")
+            .body(Empty::<Bytes>::new())?;
+        let res = client.request(req).await?;
+        assert_eq!(res.status(), StatusCode::OK);
+        assert!(!res.headers().contains_key("access-control-allow-origin"));
+        context.stop().await;
+
+        // Origins the middleware would fail on are refused before any worker starts
+        for origins in [
+            vec!["localhost:3000"],
+            vec!["http://local host"],
+            vec!["ftp://localhost"],
+            vec!["http://localhost:3000/app"],
+            vec!["*", "http://localhost:3000"],
+        ] {
+            let config = Config {
+                cors: Some(CorsConfig {
+                    allowed_origins: origins.iter().map(|o| o.to_string()).collect(),
+                    ..CorsConfig::default()
+                }),
+                ..Config::new()
+            };
+            let err = http_service::create_server(&config).err().unwrap();
+            assert_eq!(
+                err.kind(),
+                std::io::ErrorKind::InvalidInput,
+                "{:?}",
+                origins
+            );
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,576 @@
+// Copyright Cartesi and individual authors (see AUTHORS)
+// SPDX-License-Identifier: Apache-2.0
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+#[cfg(all(test, unix))]
+mod tests {
+    use salsa::app::{self, AppManifest};
+    use salsa::config::{Config, ReadinessProbe, ResourceLimits, RestartPolicy};
+    use salsa::dapp_process::{self, ProcessGroup, ProcessStatus};
+    use salsa::manifest::{ProcessManifest, ProcessSpec};
+    use salsa::mock::MockRollup;
+    use salsa::rollup::client::RollupClient;
+    use salsa::rollup::ExceptionReport;
+    use salsa::shutdown;
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    const HOST: &str = "127.0.0.1";
+
+    fn args(command: &[&str]) -> Vec<String> {
+        command.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    #[tokio::test]
+    async fn test_restart_on_failure_until_exhausted() -> Result<(), Box<dyn std::error::Error>> {
+        let mock = MockRollup::new();
+        let server = mock.serve(HOST, 5504)?;
+        let handle = server.handle();
+        tokio::spawn(server);
+        let config = Config {
+            rollup_url: format!("http://{}:5504", HOST),
+            dapp_restart: RestartPolicy::OnFailure,
+            dapp_max_restarts: 2,
+            dapp_restart_backoff: Duration::from_millis(10),
+            ..Config::new()
+        };
+        let rollup = RollupClient::new(&config)?;
+
+        let status = Arc::new(ProcessStatus::default());
+        dapp_process::run(&config, &rollup, args(&["false"]), status.clone()).await;
+        let state = status.snapshot();
+        assert_eq!((state.starts, state.restarts), (3, 2));
+        assert!(state.exhausted && !state.running);
+        let exceptions = mock.exceptions();
+        assert_eq!(exceptions.len(), 1);
+        let report: ExceptionReport = serde_json::from_slice(&exceptions[0])?;
+        assert_eq!(report.kind, dapp_process::DAPP_EXIT);
+        assert!(report.message.contains("giving up after 2 restarts"));
+        assert_eq!((report.exit_code, report.restarts), (Some(1), 2));
+        assert_eq!(report.command, vec!["false"]);
+
+        handle.stop(true).await;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_successful_exit_not_restarted_on_failure_policy(
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let mock = MockRollup::new();
+        let server = mock.serve(HOST, 5514)?;
+        let handle = server.handle();
+        tokio::spawn(server);
+        let config = Config {
+            rollup_url: format!("http://{}:5514", HOST),
+            dapp_restart: RestartPolicy::OnFailure,
+            ..Config::new()
+        };
+        let rollup = RollupClient::new(&config)?;
+
+        let status = Arc::new(ProcessStatus::default());
+        dapp_process::run(&config, &rollup, args(&["true"]), status.clone()).await;
+        let state = status.snapshot();
+        assert_eq!((state.starts, state.restarts), (1, 0));
+        assert_eq!(mock.exceptions().len(), 1);
+
+        handle.stop(true).await;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_stderr_tail_in_exception() -> Result<(), Box<dyn std::error::Error>> {
+        let mock = MockRollup::new();
+        let server = mock.serve(HOST, 5524)?;
+        let handle = server.handle();
+        tokio::spawn(server);
+        let config = Config {
+            rollup_url: format!("http://{}:5524", HOST),
+            dapp_stderr_tail: 2,
+            ..Config::new()
+        };
+        let rollup = RollupClient::new(&config)?;
+
+        let status = Arc::new(ProcessStatus::default());
+        let script = "echo out; echo one >&2; echo two >&2; echo three >&2; exit 3";
+        dapp_process::run(&config, &rollup, args(&["sh", "-c", script]), status).await;
+        let report: ExceptionReport = serde_json::from_slice(&mock.exceptions()[0])?;
+        assert_eq!(report.stderr_tail, vec!["two", "three"]);
+        assert_eq!((report.exit_code, report.signal), (Some(3), None));
+        assert!(report.started_at_ms.unwrap() <= report.exited_at_ms.unwrap());
+
+        handle.stop(true).await;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_start_failure_report() -> Result<(), Box<dyn std::error::Error>> {
+        let mock = MockRollup::new();
+        let server = mock.serve(HOST, 5534)?;
+        let handle = server.handle();
+        tokio::spawn(server);
+        let config = Config {
+            rollup_url: format!("http://{}:5534", HOST),
+            ..Config::new()
+        };
+        let rollup = RollupClient::new(&config)?;
+
+        let status = Arc::new(ProcessStatus::default());
+        dapp_process::run(&config, &rollup, args(&["/nonexistent/dapp"]), status).await;
+        let report: ExceptionReport = serde_json::from_slice(&mock.exceptions()[0])?;
+        assert_eq!(report.kind, dapp_process::DAPP_START_FAILURE);
+        assert!(report.started_at_ms.is_none() && report.exit_code.is_none());
+
+        handle.stop(true).await;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_shutdown_forwards_signal_and_kills_after_grace(
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let mock = MockRollup::new();
+        let server = mock.serve(HOST, 5544)?;
+        let handle = server.handle();
+        tokio::spawn(server);
+        let config = Config {
+            rollup_url: format!("http://{}:5544", HOST),
+            dapp_restart: RestartPolicy::Always,
+            shutdown_grace: Duration::from_millis(500),
+            ..Config::new()
+        };
+        let rollup = RollupClient::new(&config)?;
+
+        for (script, killed) in [("sleep 30", false), ("trap '' TERM; sleep 30", true)] {
+            let status = Arc::new(ProcessStatus::default());
+            let dapp_config = config.clone();
+            let dapp_rollup = rollup.clone();
+            let dapp_status = status.clone();
+            let dapp = tokio::spawn(async move {
+                dapp_process::run(
+                    &dapp_config,
+                    &dapp_rollup,
+                    args(&["sh", "-c", script]),
+                    dapp_status,
+                )
+                .await
+            });
+            tokio::time::sleep(Duration::from_millis(300)).await;
+            status.shutdown(shutdown::SIGTERM);
+            tokio::time::timeout(Duration::from_secs(5), dapp).await??;
+            let state = status.snapshot();
+            assert_eq!((state.killed, state.restarts), (killed, 0));
+        }
+        assert!(mock.exceptions().is_empty());
+
+        handle.stop(true).await;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_readiness_probe_and_startup_timeout() -> Result<(), Box<dyn std::error::Error>> {
+        let mock = MockRollup::new();
+        let server = mock.serve(HOST, 5554)?;
+        let handle = server.handle();
+        tokio::spawn(server);
+        let ready_file = std::env::temp_dir().join(format!("salsa-ready-{}", std::process::id()));
+        let _ = std::fs::remove_file(&ready_file);
+        let config = Config {
+            rollup_url: format!("http://{}:5554", HOST),
+            dapp_readiness: Some(ReadinessProbe::File(ready_file.clone())),
+            dapp_startup_timeout: Duration::from_millis(500),
+            shutdown_grace: Duration::from_millis(500),
+            ..Config::new()
+        };
+        let rollup = RollupClient::new(&config)?;
+
+        // Becomes ready once the file is touched
+        let status = Arc::new(ProcessStatus::supervised());
+        assert!(!status.is_ready());
+        let script = format!("sleep 0.2; touch {}; sleep 30", ready_file.display());
+        let dapp_config = config.clone();
+        let dapp_rollup = rollup.clone();
+        let dapp_status = status.clone();
+        let dapp = tokio::spawn(async move {
+            dapp_process::run(
+                &dapp_config,
+                &dapp_rollup,
+                args(&["sh", "-c", &script]),
+                dapp_status,
+            )
+            .await
+        });
+        tokio::time::sleep(Duration::from_millis(400)).await;
+        assert!(status.is_ready());
+        status.shutdown(shutdown::SIGTERM);
+        tokio::time::timeout(Duration::from_secs(5), dapp).await??;
+        assert!(!status.is_ready());
+        std::fs::remove_file(&ready_file)?;
+
+        // Never ready, terminated and reported without restarting
+        let status = Arc::new(ProcessStatus::supervised());
+        let config = Config {
+            dapp_restart: RestartPolicy::Always,
+            ..config
+        };
+        dapp_process::run(&config, &rollup, args(&["sleep", "30"]), status.clone()).await;
+        let state = status.snapshot();
+        assert_eq!(
+            (state.starts, state.ready, state.running),
+            (1, false, false)
+        );
+        let report: ExceptionReport = serde_json::from_slice(&mock.exceptions()[0])?;
+        assert_eq!(report.kind, dapp_process::DAPP_NOT_READY);
+        assert_eq!(report.signal, Some(shutdown::SIGTERM));
+
+        handle.stop(true).await;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_manifest_starts_processes_in_order() -> Result<(), Box<dyn std::error::Error>> {
+        let mock = MockRollup::new();
+        let server = mock.serve(HOST, 5564)?;
+        let handle = server.handle();
+        tokio::spawn(server);
+        let dir = std::env::temp_dir().join(format!("salsa-manifest-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir)?;
+        let manifest_path = dir.join("processes.toml");
+        let manifest = format!(
+            r#"
+[[processes]]
+name = "dapp"
+command = ["sh", "-c", "test -f db.sock && test \"$GREETING\" = hi && sleep 30"]
+cwd = "{dir}"
+env = {{ GREETING = "hi" }}
+depends_on = ["db"]
+
+[[processes]]
+name = "db"
+command = ["sh", "-c", "sleep 0.3; touch db.sock; sleep 30"]
+cwd = "{dir}"
+ready = {{ file = "{dir}/db.sock" }}
+"#,
+            dir = dir.display()
+        );
+        std::fs::write(&manifest_path, manifest)?;
+        let mut manifest = ProcessManifest::load(&manifest_path)?;
+        let config = Config {
+            rollup_url: format!("http://{}:5564", HOST),
+            shutdown_grace: Duration::from_millis(500),
+            ..Config::new()
+        };
+        let rollup = RollupClient::new(&config)?;
+
+        let processes = manifest.start_order()?;
+        assert_eq!(processes[0].name, "db");
+        let group = Arc::new(ProcessGroup::new(&processes));
+        let task_group = group.clone();
+        let task = tokio::spawn(async move {
+            dapp_process::run_group(&config, &rollup, processes, task_group).await
+        });
+        tokio::time::sleep(Duration::from_millis(1000)).await;
+        let states = group.snapshot();
+        assert!(group.is_ready());
+        assert!(states
+            .iter()
+            .all(|state| state.running && state.starts == 1));
+        assert_eq!(group.dapp().unwrap().snapshot().name, "dapp");
+
+        group.shutdown(shutdown::SIGTERM);
+        tokio::time::timeout(Duration::from_secs(5), task).await??;
+        assert!(group.snapshot().iter().all(|state| !state.running));
+        assert!(!group.killed());
+        assert!(mock.exceptions().is_empty());
+
+        manifest.processes[1].depends_on = vec!["dapp".to_string()];
+        assert!(manifest.start_order().is_err());
+
+        // Readiness is reported for the dapp, a manifest without one is refused
+        manifest.processes[1].depends_on.clear();
+        manifest.processes[0].name = "app".to_string();
+        assert!(manifest
+            .start_order()
+            .unwrap_err()
+            .contains("no 'dapp' process"));
+
+        // Misspelled keys are refused rather than ignored
+        let typo =
+            "[[processes]]\nname = \"dapp\"\ncommand = [\"true\"]\nrestart_polcy = \"always\"\n";
+        std::fs::write(&manifest_path, typo)?;
+        let err = ProcessManifest::load(&manifest_path).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+        assert!(err.to_string().contains("restart_polcy"), "{}", err);
+
+        std::fs::remove_dir_all(&dir)?;
+        handle.stop(true).await;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_group_shutdown_takes_one_grace_period() -> Result<(), Box<dyn std::error::Error>>
+    {
+        let mock = MockRollup::new();
+        let server = mock.serve(HOST, 5694)?;
+        let handle = server.handle();
+        tokio::spawn(server);
+        let config = Config {
+            rollup_url: format!("http://{}:5694", HOST),
+            shutdown_grace: Duration::from_millis(500),
+            ..Config::new()
+        };
+        let rollup = RollupClient::new(&config)?;
+        let mut manifest = ProcessManifest::default();
+        for name in ["db", "cache", "queue"] {
+            let mut process =
+                ProcessSpec::dapp(args(&["sh", "-c", "trap '' TERM; exec sleep 30"]), &config);
+            process.name = name.to_string();
+            manifest.processes.push(process);
+        }
+        manifest.add_dapp(ProcessSpec::dapp(args(&["sleep", "30"]), &config))?;
+        let processes = manifest.start_order()?;
+        let group = Arc::new(ProcessGroup::new(&processes));
+        let task_group = group.clone();
+        let task = tokio::spawn(async move {
+            dapp_process::run_group(&config, &rollup, processes, task_group).await
+        });
+        tokio::time::sleep(Duration::from_millis(500)).await;
+        assert!(group.snapshot().iter().all(|state| state.running));
+
+        // The processes ignoring the signal are killed after the same grace period
+        let started = std::time::Instant::now();
+        group.shutdown(shutdown::SIGTERM);
+        tokio::time::timeout(Duration::from_secs(5), task).await??;
+        assert!(
+            started.elapsed() < Duration::from_millis(1000),
+            "{:?}",
+            started.elapsed()
+        );
+        assert!(group.killed());
+        assert!(group.snapshot().iter().all(|state| !state.running));
+
+        handle.stop(true).await;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_resource_limits() -> Result<(), Box<dyn std::error::Error>> {
+        let mock = MockRollup::new();
+        let server = mock.serve(HOST, 5574)?;
+        let handle = server.handle();
+        tokio::spawn(server);
+        let config = Config {
+            rollup_url: format!("http://{}:5574", HOST),
+            shutdown_grace: Duration::from_millis(500),
+            dapp_limits: ResourceLimits {
+                open_files: Some(64),
+                cpu_time: Some(1),
+                ..ResourceLimits::default()
+            },
+            ..Config::new()
+        };
+        let rollup = RollupClient::new(&config)?;
+
+        // The rlimits are set before the dapp executes, and what the dapp logs does not make
+        // its exit a limit being exceeded
+        let script = "ulimit -n >&2; echo 'Too many open files' >&2; exit 1";
+        let status = Arc::new(ProcessStatus::default());
+        dapp_process::run(&config, &rollup, args(&["sh", "-c", script]), status).await;
+        let report: ExceptionReport = serde_json::from_slice(&mock.exceptions()[0])?;
+        assert_eq!(report.kind, dapp_process::DAPP_EXIT);
+        assert_eq!(report.stderr_tail, vec!["64", "Too many open files"]);
+        assert!(!report.details.contains_key("limit"));
+
+        let status = Arc::new(ProcessStatus::default());
+        dapp_process::run(
+            &config,
+            &rollup,
+            args(&["sh", "-c", "while :; do :; done"]),
+            status,
+        )
+        .await;
+        let report: ExceptionReport = serde_json::from_slice(&mock.exceptions()[1])?;
+        assert_eq!(report.kind, dapp_process::DAPP_LIMIT_EXCEEDED);
+        assert_eq!(report.details["limit"], "cpu_time");
+        assert_eq!(report.details["limit_value"], 1);
+
+        let config = Config {
+            dapp_limits: ResourceLimits {
+                wall_clock: Some(1),
+                ..ResourceLimits::default()
+            },
+            ..config
+        };
+        let status = Arc::new(ProcessStatus::default());
+        dapp_process::run(&config, &rollup, args(&["sleep", "30"]), status).await;
+        let report: ExceptionReport = serde_json::from_slice(&mock.exceptions()[2])?;
+        assert_eq!(report.kind, dapp_process::DAPP_LIMIT_EXCEEDED);
+        assert_eq!(report.details["limit"], "wall_clock");
+        assert_eq!(report.signal, Some(shutdown::SIGTERM));
+
+        // Running out of file descriptors is seen from the ones held when the call failed,
+        // and a dapp may also tell by exiting with EMFILE or ENOMEM
+        let config = Config {
+            dapp_limits: ResourceLimits {
+                address_space: Some(1 << 40),
+                open_files: Some(8),
+                ..ResourceLimits::default()
+            },
+            ..config
+        };
+        let script = "exec 3</dev/null 4</dev/null 5</dev/null 6</dev/null 7</dev/null; \
+            i=0; while [ $i -lt 200000 ]; do i=$((i+1)); done; exec 8</dev/null";
+        let status = Arc::new(ProcessStatus::default());
+        dapp_process::run(&config, &rollup, args(&["sh", "-c", script]), status).await;
+        let report: ExceptionReport = serde_json::from_slice(&mock.exceptions()[3])?;
+        assert_eq!(report.kind, dapp_process::DAPP_LIMIT_EXCEEDED);
+        assert_eq!(report.details["limit"], "open_files");
+        assert_eq!(report.details["limit_value"], 8);
+        for (i, (code, limit)) in [("24", "open_files"), ("12", "address_space")]
+            .into_iter()
+            .enumerate()
+        {
+            let status = Arc::new(ProcessStatus::default());
+            let script = format!("exit {}", code);
+            dapp_process::run(&config, &rollup, args(&["sh", "-c", &script]), status).await;
+            let report: ExceptionReport = serde_json::from_slice(&mock.exceptions()[4 + i])?;
+            assert_eq!(report.kind, dapp_process::DAPP_LIMIT_EXCEEDED);
+            assert_eq!(report.details["limit"], limit);
+        }
+
+        handle.stop(true).await;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_dapp_env_and_workdir() -> Result<(), Box<dyn std::error::Error>> {
+        let mock = MockRollup::new();
+        let server = mock.serve(HOST, 5584)?;
+        let handle = server.handle();
+        tokio::spawn(server);
+        let config = Config {
+            rollup_url: format!("http://{}:5584", HOST),
+            dapp_env: [("GREETING".to_string(), "hi".to_string())].into(),
+            dapp_workdir: Some(std::env::temp_dir()),
+            dapp_clear_env: true,
+            ..Config::new()
+        };
+        let rollup = RollupClient::new(&config)?;
+
+        std::env::set_var("SALSA_TEST_INHERITED", "1");
+        let script = "echo $SALSA_URL $ROLLUP_HTTP_SERVER_URL $IPFS_API_URL >&2; \
+            echo $GREETING ${SALSA_TEST_INHERITED:-cleared} >&2; pwd -P >&2";
+        let status = Arc::new(ProcessStatus::default());
+        dapp_process::run(&config, &rollup, args(&["/bin/sh", "-c", script]), status).await;
+        let report: ExceptionReport = serde_json::from_slice(&mock.exceptions()[0])?;
+        assert_eq!(
+            report.stderr_tail,
+            vec![
+                "http://127.0.0.1:5005 http://127.0.0.1:5584 http://127.0.0.1:5001".to_string(),
+                "hi cleared".to_string(),
+                std::env::temp_dir().canonicalize()?.display().to_string(),
+            ]
+        );
+
+        handle.stop(true).await;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_app_manifest_dapp() -> Result<(), Box<dyn std::error::Error>> {
+        let mock = MockRollup::new();
+        let server = mock.serve(HOST, 5594)?;
+        let handle = server.handle();
+        tokio::spawn(server);
+        let dir = std::env::temp_dir().join(format!("salsa-app-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir)?;
+        std::fs::write(
+            dir.join("app.json"),
+            r#"{"entrypoint": "start.sh", "args": ["one"], "env": {"A": "app", "B": "app"}}"#,
+        )?;
+        std::fs::write(
+            dir.join("start.sh"),
+            "#!/bin/sh\necho $1 $A $B >&2; test -f app.json && exit 7; exit 1\n",
+        )?;
+        let config = Config {
+            rollup_url: format!("http://{}:5594", HOST),
+            dapp_env: [("B".to_string(), "cli".to_string())].into(),
+            ..Config::new()
+        };
+        let rollup = RollupClient::new(&config)?;
+
+        assert!(AppManifest::parse(br#"{"entrypoint": ""}"#).is_err());
+        assert!(AppManifest::parse(br#"{"args": []}"#).is_err());
+        assert!(AppManifest::parse(br#"{"entrypoint": "../x"}"#).is_err());
+        assert!(AppManifest::parse(br#"{"entrypoint": "bin/../../x"}"#).is_err());
+        assert!(AppManifest::parse(br#"{"entrypoint": "/bin/sh"}"#).is_err());
+        assert!(AppManifest::parse(br#"{"entrypoint": "x", "arg": ["-v"]}"#).is_err());
+        #[cfg(unix)]
+        {
+            // A link out of the app is not made executable
+            let outside = std::env::temp_dir().join(format!("salsa-out-{}", std::process::id()));
+            std::fs::write(&outside, "")?;
+            std::os::unix::fs::symlink(&outside, dir.join("link"))?;
+            let manifest = AppManifest::parse(br#"{"entrypoint": "link"}"#)?;
+            assert!(manifest.dapp(&dir, &config).is_err());
+            std::fs::remove_file(dir.join("link"))?;
+            std::fs::remove_file(&outside)?;
+        }
+
+        // The pinned CID covers the whole app, in either CID version
+        let digest = <sha2::Sha256 as sha2::Digest>::digest(b"some app");
+        let multihash = cid::multihash::Multihash::wrap(0x12, &digest)?;
+        let app = cid::Cid::new_v1(0x70, multihash);
+        let v0 = cid::Cid::new_v0(multihash)?.to_string();
+        assert!(app::check_pinned(&app, None).is_ok());
+        assert!(app::check_pinned(&app, Some(&app.to_string())).is_ok());
+        assert!(app::check_pinned(&app, Some(&v0)).is_ok());
+        let other = cid::Cid::new_v1(0x55, multihash).to_string();
+        assert!(app::check_pinned(&app, Some(&other)).is_err());
+        assert!(app::check_pinned(&app, Some("not a cid")).is_err());
+
+        // Only an app directory salsa fetched into before is replaced
+        let app_dir = std::env::temp_dir().join(format!("salsa-app-dir-{}", std::process::id()));
+        std::fs::create_dir_all(&app_dir)?;
+        app::prepare_dir(&app_dir)?;
+        std::fs::write(app_dir.join("old"), "")?;
+        app::prepare_dir(&app_dir)?;
+        assert!(!app_dir.join("old").exists());
+        std::fs::remove_file(app_dir.join(".salsa-app"))?;
+        std::fs::write(app_dir.join("mine"), "")?;
+        let error = app::prepare_dir(&app_dir).unwrap_err();
+        assert_eq!(error.kind(), std::io::ErrorKind::AlreadyExists);
+        assert!(app_dir.join("mine").exists());
+        std::fs::remove_dir_all(&app_dir)?;
+
+        let dapp = AppManifest::load(&dir)?.dapp(&dir, &config)?;
+        assert_eq!(dapp.cwd.as_deref(), Some(dir.as_path()));
+        // Started from the app directory, with the manifest env under the configured one
+        let mut group = ProcessManifest::default();
+        group.add_dapp(dapp)?;
+        let processes = group.start_order()?;
+        let group = Arc::new(ProcessGroup::new(&processes));
+        dapp_process::run_group(&config, &rollup, processes, group).await;
+        let report: ExceptionReport = serde_json::from_slice(&mock.exceptions()[0])?;
+        assert_eq!(
+            (report.exit_code, report.stderr_tail),
+            (Some(7), vec!["one app cli".to_string()])
+        );
+
+        std::fs::remove_dir_all(&dir)?;
+        handle.stop(true).await;
+        Ok(())
+    }
+}
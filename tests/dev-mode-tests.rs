@@ -0,0 +1,101 @@
+// Copyright Cartesi and individual authors (see AUTHORS)
+// SPDX-License-Identifier: Apache-2.0
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+#[cfg(test)]
+mod tests {
+    use cid::Cid;
+    use salsa::config::{Config, DevConfig};
+    use salsa::mock::RESPONSE_NOT_FOUND;
+    use salsa::rollup::{
+        client::{RollupClient, RollupError},
+        CURRENT_STATE_CID, EXTERNALIZE_STATE, IPFS_GET_BLOCK, KECCAK256_NAMESPACE, METADATA,
+        SET_STATE_CID,
+    };
+    use sha3::{Digest, Sha3_256};
+    use std::fs;
+
+    #[tokio::test]
+    async fn test_dev_local_backend() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = std::env::temp_dir().join(format!("salsa-dev-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("preimages"))?;
+        fs::write(dir.join("preimages").join("data_id"), b"some preimage")?;
+        fs::write(
+            dir.join("metadata.json"),
+            r#"{"lambada-app": "bafkreihdwdcefgh4dqkjv67uzcmw7ojee6xedzdetojuzjevtenxquvyku", "greeting": "0x6869"}"#,
+        )?;
+        let config = Config {
+            dev: Some(DevConfig {
+                data_dir: dir.join("data"),
+                metadata_file: Some(dir.join("metadata.json")),
+                preimages_dir: Some(dir.join("preimages")),
+            }),
+            ..Config::new()
+        };
+        let rollup = RollupClient::new(&config)?;
+
+        let app = rollup
+            .call(METADATA, Sha3_256::digest(b"lambada-app"))
+            .await?;
+        assert_eq!(
+            Cid::try_from(app.response_bytes()?)?.to_string(),
+            "bafkreihdwdcefgh4dqkjv67uzcmw7ojee6xedzdetojuzjevtenxquvyku"
+        );
+        let greeting = rollup.call(METADATA, Sha3_256::digest(b"greeting")).await?;
+        assert_eq!(greeting.response_bytes()?, b"hi");
+
+        let preimage = rollup.call(KECCAK256_NAMESPACE, b"data_id").await?;
+        assert_eq!(preimage.response_bytes()?, b"some preimage");
+        let escaping = rollup
+            .call(KECCAK256_NAMESPACE, b"../metadata.json")
+            .await?;
+        assert_eq!(escaping.response_code, RESPONSE_NOT_FOUND);
+        let missing = rollup.call(KECCAK256_NAMESPACE, b"other_id").await?;
+        assert_eq!(missing.response_code, RESPONSE_NOT_FOUND);
+        // Failing to read an entry is not mistaken for the rollup server being unreachable
+        fs::create_dir(dir.join("preimages").join("unreadable"))?;
+        let result = rollup.call(KECCAK256_NAMESPACE, b"unreadable").await;
+        assert!(
+            matches!(result, Err(RollupError::InvalidResponse(_))),
+            "{:?}",
+            result
+        );
+
+        // The state CID survives a restart of salsa
+        let state = Cid::try_from("bafkreihdwdcefgh4dqkjv67uzcmw7ojee6xedzdetojuzjevtenxquvyku")?;
+        rollup.call(SET_STATE_CID, state.to_bytes()).await?;
+        let restarted = RollupClient::new(&config)?;
+        let current = restarted.call(CURRENT_STATE_CID, []).await?;
+        assert_eq!(current.response_bytes()?, state.to_bytes());
+
+        rollup.call(EXTERNALIZE_STATE, b"some block").await?;
+        let cid = Cid::new_v1(
+            0x55,
+            cid::multihash::Multihash::wrap(
+                0x12,
+                &<sha2::Sha256 as sha2::Digest>::digest(b"some block"),
+            )?,
+        );
+        let block = restarted.call(IPFS_GET_BLOCK, cid.to_bytes()).await?;
+        assert_eq!(block.response_bytes()?, b"some block");
+        let unknown = Cid::new_v1(0x55, cid::multihash::Multihash::wrap(0x12, &[0; 32])?);
+        let missing = restarted.call(IPFS_GET_BLOCK, unknown.to_bytes()).await?;
+        assert_eq!(missing.response_code, RESPONSE_NOT_FOUND);
+
+        fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,57 @@
+// Copyright Cartesi and individual authors (see AUTHORS)
+// SPDX-License-Identifier: Apache-2.0
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+mod common;
+
+#[cfg(test)]
+mod tests {
+    use crate::common::{get, start};
+    use hyper::StatusCode;
+
+    #[tokio::test]
+    async fn test_health_ready_and_version() -> Result<(), Box<dyn std::error::Error>> {
+        let context = start(5444, 5445)?;
+        let (status, body) = get(context.salsa_address.clone() + "/health").await?;
+        assert_eq!(
+            (status, body),
+            (StatusCode::OK, br#"{"status":"ok"}"#.to_vec())
+        );
+
+        // No process is supervised, readiness only depends on the backends
+        let (status, body) = get(context.salsa_address.clone() + "/ready").await?;
+        let readiness: serde_json::Value = serde_json::from_slice(&body)?;
+        assert_eq!(readiness["rollup"]["reachable"], true);
+        let ready = readiness["ipfs"]["reachable"] == true;
+        assert_eq!(readiness["ready"], ready);
+        let expected = if ready {
+            StatusCode::OK
+        } else {
+            StatusCode::SERVICE_UNAVAILABLE
+        };
+        assert_eq!(status, expected);
+
+        let (status, body) = get(context.salsa_address.clone() + "/version").await?;
+        assert_eq!(status, StatusCode::OK);
+        let version: serde_json::Value = serde_json::from_slice(&body)?;
+        assert_eq!(version["version"], env!("CARGO_PKG_VERSION"));
+        assert!(version["git_commit"].is_string());
+        assert_eq!(version["config"]["http_port"], 5445);
+        assert_eq!(version["config"]["gio_timeout"], 30000);
+
+        context.stop().await;
+        Ok(())
+    }
+}
@@ -26,10 +26,18 @@ mod tests {
     };
     use hyper_util::rt::TokioIo;
     use rstest::*;
-    use salsa::{config::Config, http_service, utils};
+    use salsa::rollup::{
+        client::{RollupClient, RollupError},
+        Exception, HINT, IPFS_GET_BLOCK, METADATA, SET_STATE_CID,
+    };
+    use salsa::{config::Config, http_service, mock::MockRollup, utils};
     use serde::{Deserialize, Serialize};
     use serde_json::json;
-    use std::{convert::Infallible, future::Future, net::SocketAddr};
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    };
+    use std::{convert::Infallible, future::Future, net::SocketAddr, time::Duration};
     use tokio::{net::TcpListener, sync::oneshot, task};
     use tower::ServiceBuilder;
     const HOST: &str = "127.0.0.1";
@@ -66,7 +74,11 @@ mod tests {
     }
 
     async fn start_server(tx: oneshot::Sender<()>) {
-        let addr = SocketAddr::from(([127, 0, 0, 1], 5004));
+        start_server_on(5004, tx).await
+    }
+
+    async fn start_server_on(port: u16, tx: oneshot::Sender<()>) {
+        let addr = SocketAddr::from(([127, 0, 0, 1], port));
         let listener = TcpListener::bind(addr).await.unwrap();
 
         let _ = tx.send(());
@@ -106,6 +118,7 @@ mod tests {
         let http_config = Config {
             http_address: host.to_string(),
             http_port: port,
+            ..Config::new()
         };
         println!("Creating lambada http server");
         let server = http_service::create_server(&http_config)?;
@@ -380,4 +393,277 @@ mod tests {
         context.lambada_server_handle.stop(true).await;
         drop(server_task);
     }
+
+    #[rstest]
+    #[tokio::test]
+    async fn test_gio_timeout() -> Result<(), Box<dyn std::error::Error>> {
+        // A rollup server that accepts connections but never answers
+        let listener = TcpListener::bind(SocketAddr::from(([127, 0, 0, 1], 5104))).await?;
+        let hung_task = task::spawn(async move {
+            let mut connections = Vec::new();
+            loop {
+                let (stream, _) = listener.accept().await.unwrap();
+                connections.push(stream);
+            }
+        });
+        let http_config = Config {
+            http_address: HOST.to_string(),
+            http_port: 5105,
+            rollup_url: "http://127.0.0.1:5104".to_string(),
+            gio_timeout: Duration::from_millis(200),
+            gio_retries: 1,
+            gio_backoff: Duration::from_millis(10),
+            ..Config::new()
+        };
+        let server = http_service::create_server(&http_config)?;
+        let server_handle = server.handle();
+        tokio::spawn(server);
+
+        let client = utils::create_client();
+        let req = hyper::Request::builder()
+            .method(hyper::Method::GET)
+            .uri("http://127.0.0.1:5105/metadata/some_test_text")
+            .body(Empty::<Bytes>::new())
+            .expect("metadata request");
+        let res = client.request(req).await?;
+        assert_eq!(res.status(), StatusCode::GATEWAY_TIMEOUT);
+
+        server_handle.stop(true).await;
+        drop(hung_task);
+        Ok(())
+    }
+
+    #[rstest]
+    #[tokio::test]
+    async fn test_gio_retries() -> Result<(), Box<dyn std::error::Error>> {
+        // A rollup server that records each request but answers after the client gave up
+        let mock = MockRollup::new();
+        mock.set_delay(Duration::from_millis(500));
+        let mock_server = mock.serve(HOST, 5664)?;
+        let mock_handle = mock_server.handle();
+        tokio::spawn(mock_server);
+        let timeout = Duration::from_millis(100);
+        let backoff = Duration::from_millis(50);
+        let client = RollupClient::new(&Config {
+            rollup_url: "http://127.0.0.1:5664".to_string(),
+            gio_timeout: timeout,
+            gio_retries: 3,
+            gio_backoff: backoff,
+            ..Config::new()
+        })?;
+
+        // Idempotent domains are sent once more per retry, waiting twice as long each time
+        let result = client.call(METADATA, b"name").await;
+        assert!(
+            matches!(result, Err(RollupError::Timeout(_))),
+            "{:?}",
+            result
+        );
+        assert_eq!(mock.requests().len(), 4);
+        let times = mock.request_times();
+        let gaps: Vec<Duration> = times.windows(2).map(|w| w[1] - w[0]).collect();
+        for (i, gap) in gaps.iter().enumerate() {
+            assert!(*gap >= timeout + backoff * (1 << i), "{:?}", gaps);
+        }
+        assert!(gaps[0] < gaps[1] && gaps[1] < gaps[2], "{:?}", gaps);
+
+        // Others are never retried
+        let result = client.call(SET_STATE_CID, b"cid").await;
+        assert!(
+            matches!(result, Err(RollupError::Timeout(_))),
+            "{:?}",
+            result
+        );
+        assert_eq!(mock.requests().len(), 5);
+        let exception = Exception {
+            payload: format!("0x{}", hex::encode("some exception")),
+        };
+        let result = client.throw_exception(&exception).await;
+        assert!(
+            matches!(result, Err(RollupError::Timeout(_))),
+            "{:?}",
+            result
+        );
+        tokio::time::sleep(backoff * 4).await;
+        assert_eq!(mock.exceptions().len(), 1);
+        assert_eq!(mock.requests().len(), 5);
+
+        mock_handle.stop(false).await;
+        Ok(())
+    }
+
+    #[rstest]
+    #[tokio::test]
+    async fn test_fractional_shutdown_grace() -> Result<(), Box<dyn std::error::Error>> {
+        let mock = MockRollup::new();
+        mock.set_delay(Duration::from_millis(1200));
+        let mock_server = mock.serve(HOST, 5704)?;
+        let mock_handle = mock_server.handle();
+        tokio::spawn(mock_server);
+        let http_config = Config {
+            http_address: HOST.to_string(),
+            http_port: 5705,
+            rollup_url: "http://127.0.0.1:5704".to_string(),
+            shutdown_grace: Duration::from_millis(1500),
+            ..Config::new()
+        };
+        let server = http_service::create_server(&http_config)?;
+        let server_handle = server.handle();
+        tokio::spawn(server);
+
+        // Workers are checked once a second, the request still waiting on the rollup gets its
+        // answer only if the grace is rounded up rather than truncated
+        let in_flight = task::spawn(async {
+            let req = hyper::Request::builder()
+                .method(hyper::Method::GET)
+                .uri("http://127.0.0.1:5705/metadata/name")
+                .body(Empty::<Bytes>::new())
+                .expect("metadata request");
+            utils::create_client().request(req).await
+        });
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        server_handle.stop(true).await;
+        let res = in_flight.await??;
+        assert_ne!(res.status(), StatusCode::GATEWAY_TIMEOUT);
+
+        mock_handle.stop(false).await;
+        Ok(())
+    }
+
+    #[rstest]
+    #[tokio::test]
+    async fn test_ipfs_timeout() -> Result<(), Box<dyn std::error::Error>> {
+        // An IPFS daemon that accepts connections but never answers
+        let listener = TcpListener::bind(SocketAddr::from(([127, 0, 0, 1], 5674))).await?;
+        let hung_task = task::spawn(async move {
+            let mut connections = Vec::new();
+            loop {
+                let (stream, _) = listener.accept().await.unwrap();
+                connections.push(stream);
+            }
+        });
+        let http_config = Config {
+            http_address: HOST.to_string(),
+            http_port: 5675,
+            ipfs_url: "http://127.0.0.1:5674".to_string(),
+            ipfs_timeout: Duration::from_millis(200),
+            ..Config::new()
+        };
+        let server = http_service::create_server(&http_config)?;
+        let server_handle = server.handle();
+        tokio::spawn(server);
+
+        let client = utils::create_client();
+        let req = hyper::Request::builder()
+            .method(hyper::Method::DELETE)
+            .uri("http://127.0.0.1:5675/delete_state/key")
+            .body(Empty::<Bytes>::new())
+            .expect("delete_state request");
+        let res = client.request(req).await?;
+        assert_eq!(res.status(), StatusCode::GATEWAY_TIMEOUT);
+
+        server_handle.stop(true).await;
+        drop(hung_task);
+        Ok(())
+    }
+
+    #[test]
+    fn test_invalid_ipfs_url() {
+        let http_config = Config {
+            ipfs_url: "not a url".to_string(),
+            ..Config::new()
+        };
+        let error = http_service::create_server(&http_config)
+            .err()
+            .expect("invalid ipfs url is refused");
+        assert_eq!(error.kind(), std::io::ErrorKind::InvalidInput);
+        assert!(
+            error.to_string().contains("ipfs url 'not a url'"),
+            "{}",
+            error
+        );
+    }
+
+    #[rstest]
+    #[tokio::test]
+    async fn test_gio_cache() -> Result<(), Box<dyn std::error::Error>> {
+        // A rollup server that counts the GIO requests it answers
+        let listener = TcpListener::bind(SocketAddr::from(([127, 0, 0, 1], 5204))).await?;
+        let served = Arc::new(AtomicUsize::new(0));
+        let counter = served.clone();
+        let server_task = task::spawn(async move {
+            loop {
+                let (stream, _) = listener.accept().await.unwrap();
+                let counter = counter.clone();
+                tokio::spawn(async move {
+                    let svc = service_fn(move |req| {
+                        counter.fetch_add(1, Ordering::SeqCst);
+                        handle_request(req)
+                    });
+                    let _ = http1::Builder::new()
+                        .serve_connection(TokioIo::new(stream), svc)
+                        .await;
+                });
+            }
+        });
+        let config = Config {
+            rollup_url: "http://127.0.0.1:5204".to_string(),
+            ..Config::new()
+        };
+        let rollup = RollupClient::new(&config)?;
+        rollup.call(IPFS_GET_BLOCK, [1, 2, 3]).await?;
+        rollup.call(IPFS_GET_BLOCK, [1, 2, 3]).await?;
+        rollup.call(HINT, [1, 2, 3]).await?;
+        rollup.call(HINT, [1, 2, 3]).await?;
+        assert_eq!(served.load(Ordering::SeqCst), 3);
+        let stats = rollup.cache_stats().unwrap();
+        assert_eq!((stats.hits, stats.misses, stats.entries), (1, 1, 1));
+
+        let uncached = RollupClient::new(&Config {
+            gio_cache_enabled: false,
+            ..config
+        })?;
+        uncached.call(IPFS_GET_BLOCK, [1, 2, 3]).await?;
+        assert_eq!(served.load(Ordering::SeqCst), 4);
+        assert!(uncached.cache_stats().is_none());
+
+        drop(server_task);
+        Ok(())
+    }
+
+    #[rstest]
+    #[tokio::test]
+    async fn test_gio_record_replay() -> Result<(), Box<dyn std::error::Error>> {
+        let (tx, rx) = oneshot::channel();
+        let server_task = task::spawn(start_server_on(5304, tx));
+        rx.await.expect("Server failed to start");
+        let trace = std::env::temp_dir().join(format!("salsa-trace-{}.jsonl", std::process::id()));
+        let _ = std::fs::remove_file(&trace);
+
+        let recording = RollupClient::new(&Config {
+            rollup_url: "http://127.0.0.1:5304".to_string(),
+            gio_record: Some(trace.clone()),
+            ..Config::new()
+        })?;
+        recording.call(HINT, b"first").await?;
+        recording.call(IPFS_GET_BLOCK, [1, 2, 3]).await?;
+        drop(server_task);
+
+        // The rollup server is gone, answers come from the trace only
+        let replaying = RollupClient::new(&Config {
+            rollup_url: "http://127.0.0.1:5304".to_string(),
+            gio_replay: Some(trace.clone()),
+            ..Config::new()
+        })?;
+        assert_eq!(replaying.replay_remaining(), Some(2));
+        let response = replaying.call(HINT, b"first").await?;
+        assert_eq!(response.response, "0x");
+        let diverged = replaying.call(IPFS_GET_BLOCK, [3, 2, 1]).await;
+        assert!(matches!(diverged, Err(RollupError::Divergence(_))));
+        assert_eq!(replaying.replay_remaining(), Some(1));
+        assert_eq!(recording.replay_remaining(), None);
+
+        std::fs::remove_file(&trace)?;
+        Ok(())
+    }
 }
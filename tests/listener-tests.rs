@@ -0,0 +1,79 @@
+// Copyright Cartesi and individual authors (see AUTHORS)
+// SPDX-License-Identifier: Apache-2.0
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+#[cfg(all(test, unix))]
+mod tests {
+    use std::io::{Read, Write};
+    use std::os::unix::net::UnixStream;
+    use std::path::PathBuf;
+
+    use http_body_util::Empty;
+    use hyper::body::Bytes;
+    use salsa::config::{Config, Listener};
+    use salsa::{http_service, utils};
+
+    /// Send a GET over the unix socket at `path` and return the raw response
+    fn get_unix(path: PathBuf, uri: &'static str) -> std::io::Result<String> {
+        let mut stream = UnixStream::connect(path)?;
+        write!(
+            stream,
+            "GET {} HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n",
+            uri
+        )?;
+        let mut response = String::new();
+        stream.read_to_string(&mut response)?;
+        Ok(response)
+    }
+
+    #[tokio::test]
+    async fn test_several_listeners() -> Result<(), Box<dyn std::error::Error>> {
+        let socket = std::env::temp_dir().join(format!("salsa-{}.sock", std::process::id()));
+        // A socket left behind by a previous run does not prevent binding
+        drop(std::os::unix::net::UnixListener::bind(&socket)?);
+        let config = Config {
+            listen: vec![
+                Listener::Unix(socket.clone()),
+                "127.0.0.1:5614".parse()?,
+                "[::1]:5615".parse()?,
+            ],
+            ..Config::new()
+        };
+        let server = http_service::create_server(&config)?;
+        let handle = server.handle();
+        tokio::spawn(server);
+
+        let response = tokio::task::spawn_blocking({
+            let socket = socket.clone();
+            move || get_unix(socket, "/health")
+        })
+        .await??;
+        assert!(response.starts_with("HTTP/1.1 200"), "{}", response);
+        assert!(response.ends_with(r#"{"status":"ok"}"#), "{}", response);
+
+        let client = utils::create_client();
+        for url in ["http://127.0.0.1:5614/health", "http://[::1]:5615/health"] {
+            let req = hyper::Request::builder()
+                .uri(url)
+                .body(Empty::<Bytes>::new())?;
+            assert_eq!(client.request(req).await?.status(), hyper::StatusCode::OK);
+        }
+
+        handle.stop(true).await;
+        // Already removed by the server when it stops cleanly
+        let _ = std::fs::remove_file(&socket);
+        Ok(())
+    }
+}
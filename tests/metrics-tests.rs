@@ -0,0 +1,54 @@
+// Copyright Cartesi and individual authors (see AUTHORS)
+// SPDX-License-Identifier: Apache-2.0
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+mod common;
+
+#[cfg(test)]
+mod tests {
+    use crate::common::{get, start};
+    use hyper::StatusCode;
+
+    #[tokio::test]
+    async fn test_metrics() -> Result<(), Box<dyn std::error::Error>> {
+        let context = start(5454, 5455)?;
+        context.mock.set_metadata("metered", b"four");
+        let (status, _) = get(context.salsa_address.clone() + "/metadata/metered").await?;
+        assert_eq!(status, StatusCode::OK);
+        get(context.salsa_address.clone() + "/metadata/metered").await?;
+
+        let (status, body) = get(context.salsa_address.clone() + "/metrics").await?;
+        assert_eq!(status, StatusCode::OK);
+        let metrics = String::from_utf8(body)?;
+        for expected in [
+            r#"salsa_http_requests_total{method="GET",route="/metadata/{text}",status="200"}"#,
+            r#"salsa_http_request_duration_seconds_count{method="GET",route="/metadata/{text}"}"#,
+            r#"salsa_gio_requests_total{domain="metadata",response_code="0"}"#,
+            r#"salsa_gio_payload_bytes_total{direction="response",domain="metadata"}"#,
+            r#"salsa_gio_cache{stat="hits"}"#,
+            "salsa_processes 0",
+        ] {
+            assert!(
+                metrics.contains(expected),
+                "{} not in {}",
+                expected,
+                metrics
+            );
+        }
+
+        context.stop().await;
+        Ok(())
+    }
+}
@@ -0,0 +1,151 @@
+// Copyright Cartesi and individual authors (see AUTHORS)
+// SPDX-License-Identifier: Apache-2.0
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+mod common;
+
+#[cfg(test)]
+mod tests {
+    use crate::common::{get, start, HOST};
+    use http_body_util::Full;
+    use hyper::{body::Bytes, Method, StatusCode};
+    use salsa::config::Config;
+    use salsa::mock::{MockRollup, RESPONSE_NOT_FOUND};
+    use salsa::rollup::client::RollupClient;
+    use salsa::rollup::{Exception, GIOResponse, HINT, METADATA};
+    use salsa::utils;
+
+    #[tokio::test]
+    async fn test_mock_metadata_and_preimages() -> Result<(), Box<dyn std::error::Error>> {
+        let context = start(5404, 5405)?;
+        context
+            .mock
+            .set_metadata("some_test_text", b"some metadata");
+        context.mock.set_preimage("data_id", b"some preimage");
+
+        let (status, body) =
+            get(context.salsa_address.clone() + "/metadata/some_test_text").await?;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body, b"some metadata");
+
+        let (status, body) =
+            get(context.salsa_address.clone() + "/get_data/keccak256/data_id").await?;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body, b"some preimage");
+
+        let (status, _) = get(context.salsa_address.clone() + "/hint/some_hint").await?;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(context.mock.hints(), vec![b"some_hint".to_vec()]);
+        assert_eq!(context.mock.requests().len(), 3);
+
+        context.stop().await;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_mock_ipfs_blocks() -> Result<(), Box<dyn std::error::Error>> {
+        let context = start(5414, 5415)?;
+        let client = utils::create_client();
+        let req = hyper::Request::builder()
+            .method(Method::PUT)
+            .uri(context.salsa_address.clone() + "/ipfs/put/any")
+            .body(Full::new(Bytes::from_static(b"some block")))?;
+        assert_eq!(client.request(req).await?.status(), StatusCode::OK);
+
+        // CIDv1, raw codec, sha2-256 of "some block"
+        let cid = cid::Cid::new_v1(
+            0x55,
+            cid::multihash::Multihash::wrap(
+                0x12,
+                &<sha2::Sha256 as sha2::Digest>::digest(b"some block"),
+            )?,
+        );
+        assert_eq!(context.mock.block(&cid), Some(b"some block".to_vec()));
+        let (status, body) = get(format!("{}/ipfs/get/{}", context.salsa_address, cid)).await?;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body, b"some block");
+
+        context.stop().await;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_mock_scripted_responses_and_exceptions() -> Result<(), Box<dyn std::error::Error>>
+    {
+        let mock = MockRollup::new();
+        let server = mock.serve(HOST, 5424)?;
+        let handle = server.handle();
+        tokio::spawn(server);
+        let rollup = RollupClient::new(&Config {
+            rollup_url: format!("http://{}:5424", HOST),
+            ..Config::new()
+        })?;
+
+        mock.script(
+            HINT,
+            GIOResponse {
+                response_code: 42,
+                response: "0x01".to_string(),
+            },
+        );
+        assert_eq!(rollup.call(HINT, b"scripted").await?.response_code, 42);
+        assert_eq!(rollup.call(HINT, b"emulated").await?.response_code, 0);
+        let missing = rollup.call(METADATA, [0; 32]).await?;
+        assert_eq!(missing.response_code, RESPONSE_NOT_FOUND);
+
+        rollup
+            .throw_exception(&Exception {
+                payload: format!("0x{}", hex::encode("dapp exited")),
+            })
+            .await?;
+        assert_eq!(mock.exceptions(), vec![b"dapp exited".to_vec()]);
+
+        handle.stop(true).await;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_dapp_raised_exception() -> Result<(), Box<dyn std::error::Error>> {
+        let context = start(5434, 5435)?;
+        let client = utils::create_client();
+        let body =
+            r#"{"kind": "invalid_input", "message": "bad deposit", "details": {"b": 2, "a": 1}}"#;
+        let req = hyper::Request::builder()
+            .method(Method::POST)
+            .header(hyper::header::CONTENT_TYPE, "application/json")
+            .uri(context.salsa_address.clone() + "/exception")
+            .body(Full::new(Bytes::from_static(body.as_bytes())))?;
+        assert_eq!(client.request(req).await?.status(), StatusCode::OK);
+
+        let exceptions = context.mock.exceptions();
+        assert_eq!(
+            String::from_utf8(exceptions[0].clone())?,
+            r#"{"kind":"invalid_input","message":"bad deposit","command":[],"exit_code":null,"signal":null,"started_at_ms":null,"exited_at_ms":null,"restarts":0,"stderr_tail":[],"details":{"a":1,"b":2}}"#
+        );
+
+        let req = hyper::Request::builder()
+            .method(Method::POST)
+            .header(hyper::header::CONTENT_TYPE, "application/json")
+            .uri(context.salsa_address.clone() + "/exception")
+            .body(Full::new(Bytes::from_static(
+                br#"{"kind": "", "message": "untyped"}"#,
+            )))?;
+        assert_eq!(client.request(req).await?.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(context.mock.exceptions().len(), 1);
+
+        context.stop().await;
+        Ok(())
+    }
+}
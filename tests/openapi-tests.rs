@@ -0,0 +1,124 @@
+// Copyright Cartesi and individual authors (see AUTHORS)
+// SPDX-License-Identifier: Apache-2.0
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+#[cfg(test)]
+mod tests {
+    use http_body_util::Empty;
+    use hyper::{body::Bytes, Method, StatusCode};
+    use salsa::http_service::{self, ApiDoc};
+    use salsa::mock::MockRollup;
+    use salsa::{config::Config, utils};
+    use utoipa::OpenApi;
+
+    const SPEC_PATH: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/lambda.yaml");
+
+    #[test]
+    fn test_checked_in_spec_is_up_to_date() -> Result<(), Box<dyn std::error::Error>> {
+        let generated = ApiDoc::openapi().to_yaml()?;
+        // Regenerate the checked-in spec with SALSA_UPDATE_OPENAPI=1 cargo test
+        if std::env::var_os("SALSA_UPDATE_OPENAPI").is_some() {
+            std::fs::write(SPEC_PATH, &generated)?;
+        }
+        let checked_in = std::fs::read_to_string(SPEC_PATH)?;
+        assert!(
+            checked_in == generated,
+            "lambda.yaml differs from the generated spec, run SALSA_UPDATE_OPENAPI=1 cargo test"
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_openapi_json() -> Result<(), Box<dyn std::error::Error>> {
+        let config = Config {
+            http_port: 5604,
+            ..Config::new()
+        };
+        let server = http_service::create_server(&config)?;
+        let handle = server.handle();
+        tokio::spawn(server);
+
+        let client = utils::create_client();
+        let req = hyper::Request::builder()
+            .method(Method::GET)
+            .uri(format!("http://{}:5604/openapi.json", config.http_address))
+            .body(Empty::<Bytes>::new())?;
+        let res = client.request(req).await?;
+        assert_eq!(res.status(), StatusCode::OK);
+        let body = utils::response_to_bytes(res).await?;
+        let spec: serde_json::Value = serde_json::from_slice(&body)?;
+        assert_eq!(spec, serde_json::to_value(ApiDoc::openapi())?);
+        assert!(spec["paths"]["/hint/{what}"]["get"].is_object());
+
+        handle.stop(true).await;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_spec_matches_routes() -> Result<(), Box<dyn std::error::Error>> {
+        let mock = MockRollup::new();
+        let mock_server = mock.serve("127.0.0.1", 5684)?;
+        let mock_handle = mock_server.handle();
+        tokio::spawn(mock_server);
+        let config = Config {
+            http_port: 5685,
+            rollup_url: "http://127.0.0.1:5684".to_string(),
+            ..Config::new()
+        };
+        let server = http_service::create_server(&config)?;
+        let handle = server.handle();
+        tokio::spawn(server);
+
+        // No handler answers 404, so one means the router does not know the route
+        let client = utils::create_client();
+        let spec = serde_json::to_value(ApiDoc::openapi())?;
+        let paths = spec["paths"].as_object().unwrap();
+        assert!(paths.contains_key("/openapi.json"));
+        for (path, item) in paths {
+            let uri = path
+                .split('/')
+                .map(|segment| {
+                    if segment.starts_with('{') {
+                        "x"
+                    } else {
+                        segment
+                    }
+                })
+                .collect::<Vec<_>>()
+                .join("/");
+            let uri = format!("http://{}:5685{}", config.http_address, uri);
+            for method in ["GET", "HEAD", "POST", "PUT", "DELETE"] {
+                let req = hyper::Request::builder()
+                    .method(method)
+                    .uri(&uri)
+                    .body(Empty::<Bytes>::new())?;
+                let status = client.request(req).await?.status();
+                let documented = item.get(method.to_lowercase()).is_some();
+                assert_eq!(
+                    status != StatusCode::NOT_FOUND,
+                    documented,
+                    "{} {} answered {}",
+                    method,
+                    path,
+                    status
+                );
+            }
+        }
+
+        handle.stop(true).await;
+        mock_handle.stop(true).await;
+        Ok(())
+    }
+}
@@ -0,0 +1,201 @@
+// Copyright Cartesi and individual authors (see AUTHORS)
+// SPDX-License-Identifier: Apache-2.0
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+mod common;
+
+#[cfg(test)]
+mod tests {
+    use crate::common::{start, start_with, HOST};
+    use base64::engine::general_purpose::STANDARD as BASE64;
+    use base64::Engine;
+    use http_body_util::Full;
+    use hyper::{body::Bytes, Method, StatusCode};
+    use salsa::config::{AuthConfig, AuthMethod, BodyLimits, Config, Secret};
+    use salsa::rpc::{INVALID_PARAMS, INVALID_REQUEST, METHOD_NOT_FOUND, PARSE_ERROR};
+    use salsa::utils;
+    use serde_json::{json, Value};
+
+    const TOKEN: &str = "debug-token";
+
+    async fn rpc(
+        port: u16,
+        body: &str,
+        token: Option<&str>,
+    ) -> Result<(StatusCode, Value), Box<dyn std::error::Error>> {
+        let client = utils::create_client();
+        let mut req = hyper::Request::builder()
+            .method(Method::POST)
+            .uri(format!("http://{}:{}/rpc", HOST, port))
+            .header(hyper::header::CONTENT_TYPE, "application/json");
+        if let Some(token) = token {
+            req = req.header("authorization", format!("Bearer {}", token));
+        }
+        let res = client
+            .request(req.body(Full::new(Bytes::from(body.to_string())))?)
+            .await?;
+        let status = res.status();
+        let body = utils::response_to_bytes(res).await?;
+        let value = if body.is_empty() {
+            Value::Null
+        } else {
+            serde_json::from_slice(&body)?
+        };
+        Ok((status, value))
+    }
+
+    #[tokio::test]
+    async fn test_rpc_calls_and_batches() -> Result<(), Box<dyn std::error::Error>> {
+        let context = start(5644, 5645)?;
+        let mock = &context.mock;
+        mock.set_metadata("some_test_text", b"some metadata");
+        mock.set_preimage("data_id", b"some preimage");
+
+        let (status, response) = rpc(
+            5645,
+            r#"{"jsonrpc": "2.0", "method": "metadata_get", "params": {"text": "some_test_text"}, "id": 1}"#,
+            None,
+        )
+        .await?;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(
+            response,
+            json!({"jsonrpc": "2.0", "result": BASE64.encode(b"some metadata"), "id": 1})
+        );
+
+        // A block put through ipfs_put is read back through ipfs_get
+        let cid = cid::Cid::new_v1(
+            0x55,
+            cid::multihash::Multihash::wrap(
+                0x12,
+                &<sha2::Sha256 as sha2::Digest>::digest(b"some block"),
+            )?,
+        );
+        let batch = json!([
+            {"jsonrpc": "2.0", "method": "ipfs_put", "params": [BASE64.encode(b"some block")], "id": "put"},
+            {"jsonrpc": "2.0", "method": "ipfs_get", "params": [cid.to_string()], "id": "get"},
+            {"jsonrpc": "2.0", "method": "data_get", "params": ["keccak256", "data_id"], "id": 3},
+            {"jsonrpc": "2.0", "method": "hint", "params": {"what": "some_hint"}},
+            {"jsonrpc": "2.0", "method": "no_such_method", "id": 4},
+            {"jsonrpc": "2.0", "method": "data_get", "params": {"namespace": "keccak256"}, "id": 5},
+            {"jsonrpc": "1.0", "method": "hint", "id": 6},
+            7,
+        ]);
+        let (status, response) = rpc(5645, &batch.to_string(), None).await?;
+        assert_eq!(status, StatusCode::OK);
+        let responses = response.as_array().unwrap();
+        // The notification is not answered
+        assert_eq!(responses.len(), 7);
+        assert_eq!(responses[0]["id"], "put");
+        assert!(responses[0]["error"].is_null(), "{}", responses[0]);
+        assert_eq!(responses[1]["result"], BASE64.encode(b"some block"));
+        assert_eq!(responses[2]["result"], BASE64.encode(b"some preimage"));
+        assert_eq!(responses[3]["error"]["code"], METHOD_NOT_FOUND);
+        assert_eq!(responses[4]["error"]["code"], INVALID_PARAMS);
+        assert_eq!(responses[5]["error"]["code"], INVALID_REQUEST);
+        assert_eq!(responses[5]["id"], 6);
+        assert_eq!(responses[6]["error"]["code"], INVALID_REQUEST);
+        assert_eq!(responses[6]["id"], Value::Null);
+        assert_eq!(mock.hints(), vec![b"some_hint".to_vec()]);
+
+        // Only notifications
+        let (status, _) = rpc(
+            5645,
+            r#"[{"jsonrpc": "2.0", "method": "hint", "params": ["other_hint"]}]"#,
+            None,
+        )
+        .await?;
+        assert_eq!(status, StatusCode::NO_CONTENT);
+        assert_eq!(mock.hints().len(), 2);
+
+        let (_, response) = rpc(5645, "[]", None).await?;
+        assert_eq!(response["error"]["code"], INVALID_REQUEST);
+        let (_, response) = rpc(5645, "{not json", None).await?;
+        assert_eq!(response["error"]["code"], PARSE_ERROR);
+        assert_eq!(response["id"], Value::Null);
+
+        context.stop().await;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_rpc_credentials_and_limits() -> Result<(), Box<dyn std::error::Error>> {
+        let config = Config {
+            auth: AuthConfig {
+                mutate_state: Some(AuthMethod::Bearer(Secret::new(TOKEN))),
+                ..AuthConfig::default()
+            },
+            body_limits: BodyLimits {
+                ipfs_put: 8,
+                rpc: 1024,
+                ..BodyLimits::default()
+            },
+            ..Config::new()
+        };
+        let context = start_with(5654, 5655, config)?;
+        let mock = &context.mock;
+        mock.set_metadata("some_test_text", b"some metadata");
+        let batch = json!([
+            {"jsonrpc": "2.0", "method": "ipfs_put", "params": [BASE64.encode(b"small")], "id": 1},
+            {"jsonrpc": "2.0", "method": "metadata_get", "params": ["some_test_text"], "id": 2},
+        ])
+        .to_string();
+
+        // Each call is checked against the credentials of the route it mirrors
+        let (status, response) = rpc(5655, &batch, None).await?;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(response[0]["error"]["data"]["status"], 401);
+        assert_eq!(response[1]["result"], BASE64.encode(b"some metadata"));
+        let (_, response) = rpc(5655, &batch, Some("wrong-token")).await?;
+        assert_eq!(response[0]["error"]["data"]["status"], 403);
+        let (_, response) = rpc(5655, &batch, Some(TOKEN)).await?;
+        assert!(response[0]["error"].is_null(), "{}", response[0]);
+
+        // Decoded values are bounded by the limit of their route
+        let call = json!({
+            "jsonrpc": "2.0",
+            "method": "ipfs_put",
+            "params": {"block": BASE64.encode(b"nine byte")},
+            "id": 3
+        });
+        let (_, response) = rpc(5655, &call.to_string(), Some(TOKEN)).await?;
+        assert_eq!(response["error"]["data"]["status"], 413);
+        assert_eq!(response["error"]["data"]["limit"], 8);
+
+        let call = json!({
+            "jsonrpc": "2.0",
+            "method": "state_delete",
+            "params": {"key": "../app"},
+            "id": 4
+        });
+        let (_, response) = rpc(5655, &call.to_string(), Some(TOKEN)).await?;
+        assert_eq!(response["error"]["code"], INVALID_PARAMS);
+
+        // The whole request is bounded by the rpc limit
+        let call = json!({
+            "jsonrpc": "2.0",
+            "method": "hint",
+            "params": ["x".repeat(2048)],
+            "id": 5
+        });
+        let (status, _) = rpc(5655, &call.to_string(), None).await?;
+        assert_eq!(status, StatusCode::PAYLOAD_TOO_LARGE);
+        // Only the metadata_get calls and the allowed ipfs_put reached the rollup server
+        assert_eq!(mock.requests().len(), 4);
+
+        context.stop().await;
+        Ok(())
+    }
+}
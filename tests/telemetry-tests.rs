@@ -0,0 +1,117 @@
+// Copyright Cartesi and individual authors (see AUTHORS)
+// SPDX-License-Identifier: Apache-2.0
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+mod common;
+
+#[cfg(test)]
+mod tests {
+    use crate::common::{start, HOST};
+    use http_body_util::Empty;
+    use hyper::body::Bytes;
+    use salsa::config::Config;
+    use salsa::mock::MockRollup;
+    use salsa::rollup::client::RollupClient;
+    use salsa::rollup::METADATA;
+    use salsa::utils;
+
+    #[tokio::test]
+    async fn test_request_id() -> Result<(), Box<dyn std::error::Error>> {
+        let context = start(5464, 5465)?;
+        let client = utils::create_client();
+        let req = hyper::Request::builder()
+            .uri(context.salsa_address.clone() + "/health")
+            .header("x-request-id", "given-id")
+            .body(Empty::<Bytes>::new())?;
+        let res = client.request(req).await?;
+        assert_eq!(res.headers()["x-request-id"], "given-id");
+
+        let req = hyper::Request::builder()
+            .uri(context.salsa_address.clone() + "/health")
+            .body(Empty::<Bytes>::new())?;
+        let res = client.request(req).await?;
+        assert_eq!(res.headers()["x-request-id"].len(), 32);
+
+        context.stop().await;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_trace_file() -> Result<(), Box<dyn std::error::Error>> {
+        use tracing::Instrument;
+        use tracing_subscriber::layer::SubscriberExt;
+
+        let mock = MockRollup::new();
+        let mock_server = mock.serve(HOST, 5474)?;
+        let mock_handle = mock_server.handle();
+        tokio::spawn(mock_server);
+        mock.set_metadata("traced", b"value");
+        let client = RollupClient::new(&Config {
+            rollup_url: format!("http://{}:5474", HOST),
+            ..Config::new()
+        })?;
+
+        let path = std::env::temp_dir().join(format!("salsa-trace-{}.jsonl", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let subscriber =
+            tracing_subscriber::registry().with(salsa::telemetry::OtlpFileLayer::create(&path)?);
+        {
+            let _guard = tracing::subscriber::set_default(subscriber);
+            let span = tracing::info_span!("http_request", request_id = "traced-request");
+            client
+                .call(
+                    METADATA,
+                    <sha3::Sha3_256 as sha3::Digest>::digest(b"traced"),
+                )
+                .instrument(span)
+                .await?;
+        }
+
+        let spans: Vec<serde_json::Value> = std::fs::read_to_string(&path)?
+            .lines()
+            .map(|line| {
+                serde_json::from_str::<serde_json::Value>(line).unwrap()["resourceSpans"][0]
+                    ["scopeSpans"][0]["spans"][0]
+                    .clone()
+            })
+            .collect();
+        std::fs::remove_file(&path)?;
+        let [gio, request] = &spans[..] else {
+            panic!("expected two spans, got {:?}", spans);
+        };
+        assert_eq!(request["name"], "http_request");
+        assert_eq!(request["parentSpanId"], "");
+        assert_eq!(gio["name"], "gio");
+        assert_eq!(gio["kind"], 3);
+        assert_eq!(gio["traceId"], request["traceId"]);
+        assert_eq!(gio["parentSpanId"], request["spanId"]);
+        let attributes = gio["attributes"].as_array().unwrap();
+        for (key, value) in [
+            ("domain", serde_json::json!({ "stringValue": "metadata" })),
+            ("payload_bytes", serde_json::json!({ "intValue": "32" })),
+            ("response_bytes", serde_json::json!({ "intValue": "5" })),
+        ] {
+            assert!(
+                attributes.contains(&serde_json::json!({ "key": key, "value": value })),
+                "{} not in {:?}",
+                key,
+                attributes
+            );
+        }
+
+        mock_handle.stop(true).await;
+        Ok(())
+    }
+}